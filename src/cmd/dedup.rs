@@ -0,0 +1,213 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use select::{SelectColumns, Selection};
+use util;
+
+static USAGE: &'static str = "
+Removes duplicate rows from CSV data.
+
+Two rows are duplicates when the columns selected with --select are equal.
+By default, all columns are compared and the first occurrence of each row
+is kept. Kept rows are written in the same order as they appear in the input.
+
+By default, the key of every distinct row is stored in memory. Keeping the
+last occurrence with --keep-last stores all of the rows in memory. If the
+input is already sorted (or grouped) by the selected columns, use --sorted
+instead to deduplicate in constant memory.
+
+Usage:
+    xsv dedup [options] [<input>]
+
+dedup options:
+    -s, --select <arg>     Select the columns that make up the key used to
+                           compare rows. See 'xsv select --help' for the
+                           format details.
+    -l, --keep-last        Keep the last occurrence of each row instead of
+                           the first.
+    -S, --sorted           Assume the input is sorted by the selected
+                           columns, so that duplicates are adjacent. Only
+                           the previous row is kept in memory.
+    -D, --dupes-output <file>  Write the rows that were removed to <file>.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. Namely, it will be deduplicated with
+                           the rest of the rows. Otherwise, the first row will
+                           always appear as the header row in the output.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_select: SelectColumns,
+    flag_keep_last: bool,
+    flag_sorted: bool,
+    flag_dupes_output: Option<String>,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+type BoxedWriter = csv::Writer<Box<io::Write+'static>>;
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers)
+        .select(args.flag_select.clone());
+
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    rconfig.write_headers(&mut rdr, &mut wtr)?;
+    let dupes = match args.flag_dupes_output {
+        None => None,
+        Some(ref path) => {
+            let mut dwtr = Config::new(&Some(path.clone())).writer()?;
+            rconfig.write_headers(&mut rdr, &mut dwtr)?;
+            Some(dwtr)
+        }
+    };
+
+    let mut out = Output { wtr: wtr, dupes: dupes };
+    let keep_last = args.flag_keep_last;
+    if args.flag_sorted {
+        dedup_sorted(&sel, keep_last, &mut rdr, &mut out)?;
+    } else if keep_last {
+        dedup_hash_last(&sel, &mut rdr, &mut out)?;
+    } else {
+        dedup_hash_first(&sel, &mut rdr, &mut out)?;
+    }
+    out.flush()
+}
+
+/// Output sends kept rows to the main writer and, if requested, removed rows
+/// to the duplicates writer.
+struct Output {
+    wtr: BoxedWriter,
+    dupes: Option<BoxedWriter>,
+}
+
+impl Output {
+    fn keep(&mut self, record: &csv::ByteRecord) -> CliResult<()> {
+        Ok(self.wtr.write_byte_record(record)?)
+    }
+
+    fn remove(&mut self, record: &csv::ByteRecord) -> CliResult<()> {
+        if let Some(ref mut dwtr) = self.dupes {
+            dwtr.write_byte_record(record)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> CliResult<()> {
+        self.wtr.flush()?;
+        if let Some(ref mut dwtr) = self.dupes {
+            dwtr.flush()?;
+        }
+        Ok(())
+    }
+}
+
+type Key = Vec<Vec<u8>>;
+
+fn key(sel: &Selection, record: &csv::ByteRecord) -> Key {
+    sel.select(record).map(|f| f.to_vec()).collect()
+}
+
+/// Keep the first occurrence of each key, remembering every key seen.
+fn dedup_hash_first<R: io::Read>(
+    sel: &Selection,
+    rdr: &mut csv::Reader<R>,
+    out: &mut Output,
+) -> CliResult<()> {
+    let mut seen = HashSet::new();
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        if seen.insert(key(sel, &record)) {
+            out.keep(&record)?;
+        } else {
+            out.remove(&record)?;
+        }
+    }
+    Ok(())
+}
+
+/// Keep the last occurrence of each key.
+///
+/// Since a row can't be written until we know no later row shares its key,
+/// this buffers every row in memory.
+fn dedup_hash_last<R: io::Read>(
+    sel: &Selection,
+    rdr: &mut csv::Reader<R>,
+    out: &mut Output,
+) -> CliResult<()> {
+    let all = rdr.byte_records().collect::<Result<Vec<_>, _>>()?;
+    let mut last = HashMap::new();
+    for (i, record) in all.iter().enumerate() {
+        last.insert(key(sel, record), i);
+    }
+    for (i, record) in all.iter().enumerate() {
+        if last[&key(sel, record)] == i {
+            out.keep(record)?;
+        } else {
+            out.remove(record)?;
+        }
+    }
+    Ok(())
+}
+
+/// Deduplicate input whose duplicate rows are adjacent.
+fn dedup_sorted<R: io::Read>(
+    sel: &Selection,
+    keep_last: bool,
+    rdr: &mut csv::Reader<R>,
+    out: &mut Output,
+) -> CliResult<()> {
+    // `prev` is the row kept for the current key (the first row with this
+    // key or, with `keep_last`, the most recent one). With `keep_last` it
+    // hasn't been written yet.
+    let mut prev: Option<csv::ByteRecord> = None;
+    for record in rdr.byte_records() {
+        let record = record?;
+        let same = match prev {
+            None => false,
+            Some(ref prev) => sel.select(prev).eq(sel.select(&record)),
+        };
+        if !keep_last {
+            if same {
+                out.remove(&record)?;
+            } else {
+                out.keep(&record)?;
+                prev = Some(record);
+            }
+            continue;
+        }
+        if let Some(prev) = prev.take() {
+            if same {
+                out.remove(&prev)?;
+            } else {
+                out.keep(&prev)?;
+            }
+        }
+        prev = Some(record);
+    }
+    if keep_last {
+        if let Some(prev) = prev {
+            out.keep(&prev)?;
+        }
+    }
+    Ok(())
+}
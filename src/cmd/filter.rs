@@ -0,0 +1,118 @@
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use expr::Expr;
+use util;
+
+static USAGE: &'static str = "
+Filters CSV data by evaluating an expression on each row.
+
+Only rows for which the expression is true are written to the output. For
+example, this keeps rows whose 'amount' is greater than 100 and whose
+'status' isn't 'void':
+
+    xsv filter 'amount > 100 && status != \"void\"' data.csv
+
+Columns are referred to by header name, e.g., 'amount'. Names that aren't
+simple identifiers (or that clash with a function name) can be quoted with
+backticks, e.g., `Unit Price` or `2019`, which always match the header name
+exactly. When several columns have the same name, `name`[1] refers to the
+second one. Columns can also be referred to by their 1-based index, e.g.,
+#3.
+
+Fields are typed the same way as in 'xsv stats': empty fields are null,
+fields that look like integers or floats are numbers and everything else is
+a string. Comparisons between numbers are numeric, while any other
+comparison is done on the text of each value. String functions and regex
+matches always see a field's text as written, so len(zip) is 5 for '02134'.
+A null is only equal to another null (e.g., 'status == null'), and is never
+less than or greater than anything.
+
+The following operators are supported, from lowest to highest precedence:
+
+    ||                      Logical or.
+    &&                      Logical and.
+    == != < <= > >=         Comparisons.
+    =~ !~                   Regex match (or non-match) against a string
+                            literal, e.g., 'email =~ \"@corp\\.com$\"'.
+    + -                     Addition and subtraction.
+    * / %                   Multiplication, division and remainder.
+    ! -                     Logical not and negation.
+
+Parentheses can be used for grouping. Literals are numbers, strings in
+single or double quotes, true, false and null. Arithmetic on values that
+aren't numbers (or division by zero) yields null.
+
+The following functions are available:
+
+    len(s)                  The number of characters in s.
+    lower(s), upper(s)      Convert s to lowercase or uppercase.
+    trim(s)                 Remove leading and trailing whitespace from s.
+    contains(s, sub)        Whether s contains sub.
+    starts_with(s, prefix)  Whether s starts with prefix.
+    ends_with(s, suffix)    Whether s ends with suffix.
+    matches(s, regex)       Whether the regex (a string literal) matches s.
+    is_null(x)              Whether x is null (an empty field).
+
+More functions, such as substr, replace, if and coalesce, are listed in
+'xsv map --help'.
+
+A null, false, zero or an empty string counts as false. Anything else is
+true.
+
+Usage:
+    xsv filter [options] <expression> [<input>]
+    xsv filter --help
+
+filter options:
+    -v, --invert-match     Select only rows for which the expression is
+                           false.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., They are not searched, analyzed,
+                           sliced, etc.) Columns must then be referred to by
+                           index.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    arg_expression: String,
+    flag_invert_match: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+
+    let headers = rdr.byte_headers()?.clone();
+    let expr = Expr::parse(
+        &args.arg_expression, &headers, !rconfig.no_headers)?;
+
+    rconfig.write_headers(&mut rdr, &mut wtr)?;
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        let mut m = expr.eval(&record).is_truthy();
+        if args.flag_invert_match {
+            m = !m;
+        }
+        if m {
+            wtr.write_byte_record(&record)?;
+        }
+    }
+    Ok(wtr.flush()?)
+}
@@ -1,14 +1,16 @@
 use std::fs;
-use std::io;
+use std::io::{self, Write};
 
 use channel;
 use csv;
-use stats::{Frequencies, merge_all};
+use stats::{Commute, Frequencies, merge_all};
 use threadpool::ThreadPool;
 
 use CliResult;
 use config::{Config, Delimiter};
+use hyperloglog::{self, HyperLogLog};
 use index::Indexed;
+use json;
 use select::{SelectColumns, Selection};
 use util;
 
@@ -23,9 +25,34 @@ By default, there is a row for the N most frequent values for each field in the
 data. The order and number of values can be tweaked with --asc and --limit,
 respectively.
 
+With --percent, two more columns are added: the percentage of all values in
+the field that have each value, and the running total of those percentages.
+With --other, the values left out by --limit are counted together in a final
+row with the value '(other)', so that the counts of each field still add up
+to the total.
+
 Since this computes an exact frequency table, memory proportional to the
 cardinality of each column is required.
 
+With --cardinality, only the number of distinct values in each column is
+shown, formatted as:
+
+    field,cardinality
+
+Adding --approx estimates the number of distinct values with a HyperLogLog
+sketch instead, which only requires a small, constant amount of memory for
+each column. The relative standard error of the estimate is set with
+--error-rate.
+
+With --json, the output is a JSON array with an object for each field. Its
+'values' are the rows of the field's frequency table, where the empty value
+is null:
+
+    [
+      {\"field\":\"h1\",\"values\":[{\"value\":\"a\",\"count\":2}, ...]},
+      ...
+    ]
+
 Usage:
     xsv frequency [options] [<input>]
 
@@ -40,7 +67,18 @@ frequency options:
                            [default: 10]
     -a, --asc              Sort the frequency tables in ascending order by
                            count. The default is descending order.
+    -p, --percent          Add 'percent' and 'cumulative_percent' columns.
+    --other                Add an '(other)' row with the total count of the
+                           values left out by --limit.
     --no-nulls             Don't include NULLs in the frequency table.
+    --cardinality          Show the number of distinct values in each column
+                           instead of a frequency table.
+    --approx               Estimate the number of distinct values. This can
+                           only be used with --cardinality.
+    --error-rate <arg>     The relative standard error of the estimated
+                           number of distinct values. Smaller values use
+                           more memory. It must be at least 0.00204.
+                           [default: 0.01]
     -j, --jobs <arg>       The number of jobs to run in parallel.
                            This works better when the given CSV data has
                            an index already created. Note that a file handle
@@ -52,6 +90,7 @@ frequency options:
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
+    --json                 Write the output as JSON instead of CSV.
     -n, --no-headers       When set, the first row will NOT be included
                            in the frequency table. Additionally, the 'field'
                            column will be 1-based indices instead of header
@@ -66,9 +105,15 @@ struct Args {
     flag_select: SelectColumns,
     flag_limit: usize,
     flag_asc: bool,
+    flag_percent: bool,
+    flag_other: bool,
     flag_no_nulls: bool,
+    flag_cardinality: bool,
+    flag_approx: bool,
+    flag_error_rate: f64,
     flag_jobs: usize,
     flag_output: Option<String>,
+    flag_json: bool,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
 }
@@ -76,24 +121,63 @@ struct Args {
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
     let rconfig = args.rconfig();
+    if args.flag_approx && !args.flag_cardinality {
+        return fail!("--approx can only be used with --cardinality.");
+    }
+    if !(args.flag_error_rate >= hyperloglog::MIN_ERROR
+            && args.flag_error_rate < 1.0) {
+        return fail!(format!("--error-rate must be at least {} and less \
+                              than 1.", hyperloglog::MIN_ERROR));
+    }
 
-    let mut wtr = Config::new(&args.flag_output).writer()?;
     let (headers, tables) = match args.rconfig().indexed()? {
         Some(ref mut idx) if args.njobs() > 1 => args.parallel_ftables(idx),
         _ => args.sequential_ftables(),
     }?;
+    if args.flag_json {
+        return args.write_json(&headers, tables);
+    }
 
-    wtr.write_record(vec!["field", "value", "count"])?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    if args.flag_cardinality {
+        wtr.write_record(vec!["field", "cardinality"])?;
+    } else if args.flag_percent {
+        wtr.write_record(vec![
+            "field", "value", "count", "percent", "cumulative_percent",
+        ])?;
+    } else {
+        wtr.write_record(vec!["field", "value", "count"])?;
+    }
     let head_ftables = headers.into_iter().zip(tables.into_iter());
-    for (i, (header, ftab)) in head_ftables.enumerate() {
+    for (i, (header, tab)) in head_ftables.enumerate() {
         let mut header = header.to_vec();
         if rconfig.no_headers {
             header = (i+1).to_string().into_bytes();
         }
-        for (value, count) in args.counts(&ftab).into_iter() {
-            let count = count.to_string();
-            let row = vec![&*header, &*value, count.as_bytes()];
-            wtr.write_record(row)?;
+        let ftab = match tab {
+            Table::Exact(ref ftab) if !args.flag_cardinality => ftab,
+            tab => {
+                let count = tab.cardinality().to_string();
+                wtr.write_record(vec![&*header, count.as_bytes()])?;
+                continue;
+            }
+        };
+        for row in args.counts(ftab) {
+            let value =
+                if row.value.is_empty() {
+                    b"(NULL)".to_vec()
+                } else {
+                    row.value
+                };
+            let count = row.count.to_string();
+            let percent = row.percent.to_string();
+            let cumulative_percent = row.cumulative_percent.to_string();
+            let mut record = vec![&*header, &*value, count.as_bytes()];
+            if args.flag_percent {
+                record.push(percent.as_bytes());
+                record.push(cumulative_percent.as_bytes());
+            }
+            wtr.write_record(record)?;
         }
     }
     Ok(())
@@ -102,7 +186,63 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
 type ByteString = Vec<u8>;
 type Headers = csv::ByteRecord;
 type FTable = Frequencies<Vec<u8>>;
-type FTables = Vec<Frequencies<Vec<u8>>>;
+type FTables = Vec<Table>;
+
+/// Table counts the values in one column.
+///
+/// When only an estimate of the cardinality is needed, the values are added
+/// to a HyperLogLog sketch instead of a frequency table.
+#[derive(Clone)]
+enum Table {
+    Exact(FTable),
+    Approx(HyperLogLog),
+}
+
+impl Table {
+    fn add(&mut self, value: ByteString) {
+        match *self {
+            Table::Exact(ref mut ftab) => ftab.add(value),
+            Table::Approx(ref mut hll) => hll.add(&value),
+        }
+    }
+
+    fn cardinality(&self) -> u64 {
+        match *self {
+            Table::Exact(ref ftab) => ftab.cardinality(),
+            Table::Approx(ref hll) => hll.estimate(),
+        }
+    }
+}
+
+/// Count is one row of a frequency table.
+struct Count {
+    value: ByteString,
+    count: u64,
+    /// The percentage of all values in the table that are `value`.
+    percent: f64,
+    /// The sum of `percent` for this row and every row before it.
+    cumulative_percent: f64,
+}
+
+impl Commute for Table {
+    fn merge(&mut self, other: Table) {
+        match other {
+            Table::Exact(t2) => {
+                if let Table::Exact(ref mut t1) = *self {
+                    t1.merge(t2);
+                    return;
+                }
+            }
+            Table::Approx(t2) => {
+                if let Table::Approx(ref mut t1) = *self {
+                    t1.merge(t2);
+                    return;
+                }
+            }
+        }
+        unreachable!("merging exact and approximate tables");
+    }
+}
 
 impl Args {
     fn rconfig(&self) -> Config {
@@ -112,24 +252,85 @@ impl Args {
             .select(self.flag_select.clone())
     }
 
-    fn counts(&self, ftab: &FTable) -> Vec<(ByteString, u64)> {
-        let mut counts = if self.flag_asc {
+    fn counts(&self, ftab: &FTable) -> Vec<Count> {
+        let counts = if self.flag_asc {
             ftab.least_frequent()
         } else {
             ftab.most_frequent()
         };
-        if self.flag_limit > 0 {
-            counts = counts.into_iter().take(self.flag_limit).collect();
-        }
-        counts.into_iter().map(|(bs, c)| {
-            if b"" == &**bs {
-                (b"(NULL)"[..].to_vec(), c)
+        let total: u64 = counts.iter().map(|&(_, c)| c).sum();
+        let limit =
+            if self.flag_limit > 0 && self.flag_limit < counts.len() {
+                self.flag_limit
             } else {
-                (bs.clone(), c)
+                counts.len()
+            };
+        let mut rows: Vec<(ByteString, u64)> = counts[..limit].iter()
+            .map(|&(bs, c)| (bs.clone(), c))
+            .collect();
+        if self.flag_other && limit < counts.len() {
+            let other = counts[limit..].iter().map(|&(_, c)| c).sum();
+            rows.push((b"(other)".to_vec(), other));
+        }
+
+        let percent = |c: u64| c as f64 * 100.0 / total as f64;
+        let mut running = 0;
+        rows.into_iter().map(|(value, count)| {
+            running += count;
+            Count {
+                value: value,
+                count: count,
+                percent: percent(count),
+                cumulative_percent: percent(running),
             }
         }).collect()
     }
 
+    fn write_json(&self, headers: &Headers, tables: FTables) -> CliResult<()> {
+        let lossy = |s: &[u8]| String::from_utf8_lossy(s).into_owned();
+        let mut fields = vec![];
+        for (i, (header, tab)) in headers.iter().zip(tables).enumerate() {
+            let mut obj = json::Object::new();
+            if self.flag_no_headers {
+                obj.string("field", &(i+1).to_string());
+            } else {
+                obj.string("field", &lossy(header));
+            }
+            match tab {
+                Table::Exact(ref ftab) if !self.flag_cardinality => {
+                    let values: Vec<String> = self.counts(ftab).into_iter()
+                        .map(|count| {
+                            let mut row = json::Object::new();
+                            if count.value.is_empty() {
+                                row.null("value");
+                            } else {
+                                row.string("value", &lossy(&count.value));
+                            }
+                            row.number("count", &count.count.to_string());
+                            if self.flag_percent {
+                                row.number("percent",
+                                           &count.percent.to_string());
+                                row.number(
+                                    "cumulative_percent",
+                                    &count.cumulative_percent.to_string());
+                            }
+                            row.finish()
+                        })
+                        .collect();
+                    obj.raw("values", &json::inline_array(&values));
+                }
+                tab => {
+                    let count = tab.cardinality().to_string();
+                    obj.number("cardinality", &count);
+                }
+            }
+            fields.push(obj.finish());
+        }
+        let mut wtr = Config::new(&self.flag_output).io_writer()?;
+        writeln!(wtr, "{}", json::array(&fields))?;
+        Ok(wtr.flush()?)
+    }
+
     fn sequential_ftables(&self) -> CliResult<(Headers, FTables)> {
         let mut rdr = self.rconfig().reader()?;
         let (headers, sel) = self.sel_headers(&mut rdr)?;
@@ -167,8 +368,13 @@ impl Args {
             where I: Iterator<Item=csv::Result<csv::ByteRecord>> {
         let null = &b""[..].to_vec();
         let nsel = sel.normal();
-        let mut tabs: Vec<_> =
-            (0..nsel.len()).map(|_| Frequencies::new()).collect();
+        let mut tabs: Vec<_> = (0..nsel.len()).map(|_| {
+            if self.flag_approx {
+                Table::Approx(HyperLogLog::new(self.flag_error_rate))
+            } else {
+                Table::Exact(Frequencies::new())
+            }
+        }).collect();
         for row in it {
             let row = row?;
             for (i, field) in nsel.select(row.into_iter()).enumerate() {
@@ -0,0 +1,494 @@
+use std::cmp;
+use std::collections::hash_map::{Entry, HashMap};
+use std::collections::HashSet;
+use std::default::Default;
+use std::str;
+
+use channel;
+use csv;
+use stats::{Commute, OnlineStats, merge_all};
+use threadpool::ThreadPool;
+
+use CliResult;
+use cmd::stats::{FieldType, TypedMinMax, TypedSum};
+use config::{Config, Delimiter};
+use select::{SelectColumns, Selection};
+use util;
+
+static USAGE: &'static str = "
+Computes aggregates for each group of rows in CSV data.
+
+Rows are grouped by the values of the <group> columns, which are selected
+using the syntax described in 'xsv select --help'. The output has one row for
+each group, in the order that the groups first appear, with the values of the
+<group> columns followed by one column for each aggregate.
+
+Aggregates are given with --agg as a comma separated list of 'func:column'
+pairs, where column is a selection in the same syntax as <group>. If it
+selects more than one column, then the aggregate is computed for each of
+them. For example:
+
+    xsv groupby --agg 'count,sum:price,mean:3-4' region data.csv
+
+The available functions are:
+
+    count                  The number of rows in the group. With a column,
+                           the number of non-empty values in it.
+    count-distinct         The number of distinct non-empty values.
+    sum, mean              The sum or mean of the values. These are empty
+                           if the column has values that aren't numbers.
+    min, max               The smallest or largest value. Numbers are
+                           compared numerically if every value in the column
+                           is a number.
+    first, last            The first or last non-empty value.
+
+Aggregate columns are named 'column_func', e.g., 'price_sum'. A plain 'count'
+is named 'count'.
+
+Every group is kept in memory, along with every distinct value for
+count-distinct. Computing aggregates on a large file can be made much faster
+if you create an index for it first with 'xsv index'.
+
+Usage:
+    xsv groupby [options] <group> [<input>]
+    xsv groupby --help
+
+groupby options:
+    -a, --agg <specs>      The aggregates to compute. [default: count]
+    -j, --jobs <arg>       The number of jobs to run in parallel.
+                           This works better when the given CSV data has
+                           an index already created. Note that a file handle
+                           is opened for each job.
+                           When set to '0', the number of jobs is set to the
+                           number of CPUs detected.
+                           [default: 0]
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will NOT be interpreted
+                           as column names. Columns are then named using
+                           1-based indices.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+";
+
+#[derive(Clone, Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    arg_group: SelectColumns,
+    flag_agg: String,
+    flag_jobs: usize,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+
+    let mut rdr = args.rconfig().reader()?;
+    let headers = rdr.byte_headers()?.clone();
+    let sel = args.rconfig().selection(&headers)?;
+    let specs = Spec::parse_all(&args.flag_agg, &headers,
+                                !args.flag_no_headers)?;
+
+    let nrows = args.rconfig().indexed()?.map_or(0, |idx| idx.count());
+    let groups =
+        if args.njobs() > 1 && nrows > 0 {
+            args.parallel_groups(nrows, &sel, &specs)
+        } else {
+            args.compute(&sel, &specs, 0, rdr.byte_records())
+        }?;
+
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let mut row = csv::ByteRecord::new();
+    for &i in sel.iter() {
+        if args.flag_no_headers {
+            row.push_field((i + 1).to_string().as_bytes());
+        } else {
+            row.push_field(&headers[i]);
+        }
+    }
+    for spec in &specs {
+        row.push_field(spec.name.as_bytes());
+    }
+    wtr.write_byte_record(&row)?;
+
+    let mut groups: Vec<(Key, Group)> = groups.0.into_iter().collect();
+    groups.sort_by_key(|g| g.1.first_row);
+    for (key, group) in groups {
+        row.clear();
+        for field in key {
+            row.push_field(&field);
+        }
+        for (spec, agg) in specs.iter().zip(&group.aggs) {
+            row.push_field(agg.show(spec.func).as_bytes());
+        }
+        wtr.write_byte_record(&row)?;
+    }
+    Ok(wtr.flush()?)
+}
+
+/// The values of the group columns in a row.
+type Key = Vec<Vec<u8>>;
+
+impl Args {
+    fn rconfig(&self) -> Config {
+        Config::new(&self.arg_input)
+            .delimiter(self.flag_delimiter)
+            .no_headers(self.flag_no_headers)
+            .select(self.arg_group.clone())
+    }
+
+    fn njobs(&self) -> usize {
+        if self.flag_jobs == 0 { util::num_cpus() } else { self.flag_jobs }
+    }
+
+    fn parallel_groups(
+        &self,
+        nrows: u64,
+        sel: &Selection,
+        specs: &[Spec],
+    ) -> CliResult<Groups> {
+        let chunk_size = util::chunk_size(nrows as usize, self.njobs());
+        let nchunks = util::num_of_chunks(nrows as usize, chunk_size);
+
+        let pool = ThreadPool::new(self.njobs());
+        let (send, recv) = channel::bounded(0);
+        for i in 0..nchunks {
+            let (send, args, sel) = (send.clone(), self.clone(), sel.clone());
+            let specs = specs.to_vec();
+            pool.execute(move || {
+                let start = (i * chunk_size) as u64;
+                let mut idx = args.rconfig().indexed().unwrap().unwrap();
+                idx.seek(start).unwrap();
+                let it = idx.byte_records().take(chunk_size);
+                send.send(args.compute(&sel, &specs, start, it).unwrap());
+            });
+        }
+        drop(send);
+        Ok(merge_all(recv).unwrap_or_else(Default::default))
+    }
+
+    /// Aggregate the records in `it`, where `start` is the index of the
+    /// first of them in the whole input.
+    fn compute<I>(
+        &self,
+        sel: &Selection,
+        specs: &[Spec],
+        start: u64,
+        it: I,
+    ) -> CliResult<Groups>
+            where I: Iterator<Item=csv::Result<csv::ByteRecord>> {
+        let mut groups = Groups::default();
+        for (i, record) in it.enumerate() {
+            let record = record?;
+            let row = start + i as u64;
+            let key: Key =
+                sel.select(&record).map(|f| f.to_vec()).collect();
+            let group = groups.0.entry(key).or_insert_with(|| Group {
+                first_row: row,
+                aggs: specs.iter().map(|s| Agg::new(s.func)).collect(),
+            });
+            for (spec, agg) in specs.iter().zip(&mut group.aggs) {
+                agg.add(row, spec.column.map(|i| &record[i]));
+            }
+        }
+        Ok(groups)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Func {
+    Count,
+    CountDistinct,
+    Sum,
+    Mean,
+    Min,
+    Max,
+    First,
+    Last,
+}
+
+impl Func {
+    fn parse(name: &str) -> Result<Func, String> {
+        Ok(match name {
+            "count" => Func::Count,
+            "count-distinct" => Func::CountDistinct,
+            "sum" => Func::Sum,
+            "mean" => Func::Mean,
+            "min" => Func::Min,
+            "max" => Func::Max,
+            "first" => Func::First,
+            "last" => Func::Last,
+            _ => return Err(format!(
+                "Unknown aggregate function '{}'. See 'xsv groupby --help' \
+                 for the available functions.", name)),
+        })
+    }
+}
+
+/// Spec is a single aggregate, computed on one column.
+#[derive(Clone, Debug)]
+struct Spec {
+    func: Func,
+    /// The column to aggregate. This is only `None` for a plain `count`.
+    column: Option<usize>,
+    name: String,
+}
+
+impl Spec {
+    fn parse_all(
+        specs: &str,
+        headers: &csv::ByteRecord,
+        use_names: bool,
+    ) -> CliResult<Vec<Spec>> {
+        let specs_list = util::split_unquoted(specs, ',');
+        if specs_list.is_empty()
+                || specs_list.iter().any(|spec| spec.trim().is_empty()) {
+            return fail!(format!(
+                "Invalid aggregates '{}'. Aggregates can't be empty.",
+                specs));
+        }
+        let mut all = vec![];
+        for spec in specs_list {
+            let pieces = util::split_unquoted(spec, ':');
+            let func_name = pieces[0].trim();
+            let func = Func::parse(func_name)?;
+            match pieces.len() {
+                1 if func == Func::Count => {
+                    all.push(Spec {
+                        func: func,
+                        column: None,
+                        name: func_name.to_owned(),
+                    });
+                }
+                2 => {
+                    let sel = SelectColumns::parse(pieces[1])?
+                        .selection(headers, use_names)?;
+                    for &i in sel.iter() {
+                        let column = if use_names {
+                            String::from_utf8_lossy(&headers[i]).into_owned()
+                        } else {
+                            (i + 1).to_string()
+                        };
+                        all.push(Spec {
+                            func: func,
+                            column: Some(i),
+                            name: format!("{}_{}", column, func_name),
+                        });
+                    }
+                }
+                _ => {
+                    return fail!(format!(
+                        "Invalid aggregate '{}'. Aggregates must have the \
+                         form 'func:column'.", spec));
+                }
+            }
+        }
+        Ok(all)
+    }
+}
+
+/// Groups maps the values of the group columns to their aggregates.
+#[derive(Default)]
+struct Groups(HashMap<Key, Group>);
+
+impl Commute for Groups {
+    fn merge(&mut self, other: Groups) {
+        for (key, group) in other.0 {
+            match self.0.entry(key) {
+                Entry::Occupied(mut e) => e.get_mut().merge(group),
+                Entry::Vacant(e) => { e.insert(group); }
+            }
+        }
+    }
+}
+
+struct Group {
+    /// The index of the first row in the group, used to write groups in
+    /// the order that they first appear.
+    first_row: u64,
+    aggs: Vec<Agg>,
+}
+
+impl Commute for Group {
+    fn merge(&mut self, other: Group) {
+        self.first_row = cmp::min(self.first_row, other.first_row);
+        for (agg, other) in self.aggs.iter_mut().zip(other.aggs) {
+            agg.merge(other);
+        }
+    }
+}
+
+/// Agg accumulates the values of one aggregate for one group.
+///
+/// The first and last values are stored with the index of their row, so that
+/// aggregates computed on chunks of the input can be merged in any order.
+enum Agg {
+    Count(u64),
+    Distinct(HashSet<Vec<u8>>),
+    Sum(FieldType, TypedSum),
+    Mean(FieldType, OnlineStats),
+    Range(FieldType, Box<TypedMinMax>),
+    First(Option<(u64, Vec<u8>)>),
+    Last(Option<(u64, Vec<u8>)>),
+}
+
+impl Agg {
+    fn new(func: Func) -> Agg {
+        match func {
+            Func::Count => Agg::Count(0),
+            Func::CountDistinct => Agg::Distinct(HashSet::new()),
+            Func::Sum => Agg::Sum(Default::default(), Default::default()),
+            Func::Mean => Agg::Mean(Default::default(), Default::default()),
+            Func::Min | Func::Max => {
+                Agg::Range(Default::default(), Box::default())
+            }
+            Func::First => Agg::First(None),
+            Func::Last => Agg::Last(None),
+        }
+    }
+
+    /// Add the value of a column in the given row. A `sample` of `None`
+    /// stands for the whole row, and is only given to a plain `count`.
+    fn add(&mut self, row: u64, sample: Option<&[u8]>) {
+        let sample = match sample {
+            None => {
+                if let Agg::Count(ref mut n) = *self { *n += 1; }
+                return;
+            }
+            Some(sample) => sample,
+        };
+        if sample.is_empty() {
+            return;
+        }
+        match *self {
+            Agg::Count(ref mut n) => *n += 1,
+            Agg::Distinct(ref mut set) => { set.insert(sample.to_vec()); }
+            Agg::Sum(ref mut typ, ref mut sum) => {
+                typ.merge(FieldType::from_sample(sample));
+                sum.add(*typ, sample);
+            }
+            Agg::Mean(ref mut typ, ref mut online) => {
+                typ.merge(FieldType::from_sample(sample));
+                let n = str::from_utf8(sample).ok()
+                    .and_then(|s| s.parse::<f64>().ok());
+                if let Some(n) = n {
+                    online.add(n);
+                }
+            }
+            Agg::Range(ref mut typ, ref mut minmax) => {
+                let (sample_type, date) = FieldType::infer(sample, &[]);
+                typ.merge(sample_type);
+                minmax.add(*typ, sample, date);
+            }
+            Agg::First(ref mut first) => {
+                if first.is_none() {
+                    *first = Some((row, sample.to_vec()));
+                }
+            }
+            Agg::Last(ref mut last) => *last = Some((row, sample.to_vec())),
+        }
+    }
+
+    fn show(&self, func: Func) -> String {
+        match *self {
+            Agg::Count(n) => n.to_string(),
+            Agg::Distinct(ref set) => set.len().to_string(),
+            Agg::Sum(typ, ref sum) => sum.show(typ).unwrap_or_default(),
+            Agg::Mean(typ, ref online) => {
+                if typ.is_number() && online.len() > 0 {
+                    online.mean().to_string()
+                } else {
+                    "".to_owned()
+                }
+            }
+            Agg::Range(typ, ref minmax) => {
+                match minmax.show(typ) {
+                    None => "".to_owned(),
+                    Some((min, max)) => {
+                        if func == Func::Min { min } else { max }
+                    }
+                }
+            }
+            Agg::First(ref v) | Agg::Last(ref v) => {
+                v.as_ref().map_or("".to_owned(), |v| {
+                    String::from_utf8_lossy(&v.1).into_owned()
+                })
+            }
+        }
+    }
+}
+
+impl Commute for Agg {
+    fn merge(&mut self, other: Agg) {
+        match other {
+            Agg::Count(n2) => {
+                if let Agg::Count(ref mut n1) = *self {
+                    *n1 += n2;
+                    return;
+                }
+            }
+            Agg::Distinct(s2) => {
+                if let Agg::Distinct(ref mut s1) = *self {
+                    s1.extend(s2);
+                    return;
+                }
+            }
+            Agg::Sum(t2, s2) => {
+                if let Agg::Sum(ref mut t1, ref mut s1) = *self {
+                    t1.merge(t2);
+                    s1.merge(s2);
+                    return;
+                }
+            }
+            Agg::Mean(t2, o2) => {
+                if let Agg::Mean(ref mut t1, ref mut o1) = *self {
+                    t1.merge(t2);
+                    o1.merge(o2);
+                    return;
+                }
+            }
+            Agg::Range(t2, m2) => {
+                if let Agg::Range(ref mut t1, ref mut m1) = *self {
+                    t1.merge(t2);
+                    m1.merge(*m2);
+                    return;
+                }
+            }
+            Agg::First(v2) => {
+                if let Agg::First(ref mut v1) = *self {
+                    if keep_other(v1, &v2, |r1, r2| r2 < r1) {
+                        *v1 = v2;
+                    }
+                    return;
+                }
+            }
+            Agg::Last(v2) => {
+                if let Agg::Last(ref mut v1) = *self {
+                    if keep_other(v1, &v2, |r1, r2| r2 > r1) {
+                        *v1 = v2;
+                    }
+                    return;
+                }
+            }
+        }
+        unreachable!("merging different aggregates");
+    }
+}
+
+/// Returns true if the value in `other` should replace the one in `ours`,
+/// given a function that compares their row indices.
+fn keep_other<F>(
+    ours: &Option<(u64, Vec<u8>)>,
+    other: &Option<(u64, Vec<u8>)>,
+    better: F,
+) -> bool where F: Fn(u64, u64) -> bool {
+    match (ours, other) {
+        (_, &None) => false,
+        (&None, &Some(_)) => true,
+        (&Some((r1, _)), &Some((r2, _))) => better(r1, r2),
+    }
+}
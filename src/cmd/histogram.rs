@@ -0,0 +1,246 @@
+use std::cmp::Ordering;
+use std::f64;
+use std::str;
+
+use serde::de::{Deserialize, Deserializer, Error};
+use stats::Commute;
+
+use CliResult;
+use cmd::stats::{FieldType, Quantiles};
+use config::{Config, Delimiter};
+use select::SelectColumns;
+use util;
+
+static USAGE: &'static str = "
+Computes a histogram of each numeric column in CSV data.
+
+The values of each column are counted in bins, which are formatted as CSV
+data:
+
+    field,lower,upper,count
+
+Every bin includes its lower bound and excludes its upper bound, except for
+the last bin, which includes both. Empty bins are included in the output.
+
+There are three ways to choose the bins:
+
+    By default, the range between the smallest and largest value is split
+    into --bins bins of equal width.
+
+    With --quantiles, the bins are split at quantiles instead, so that each
+    bin has about the same number of values. Bins may be merged when many
+    values are the same.
+
+    With --edges, the bins are given explicitly as a comma separated list of
+    increasing numbers, e.g., '0,10,100' for two bins. Values outside of the
+    edges aren't counted.
+
+Columns are binned if every non-empty value in them is an Integer or a Float,
+using the same types as 'xsv stats'. Other columns are skipped. Empty values
+and numbers that aren't finite, like NaN or inf, are ignored.
+
+Every value is kept in memory.
+
+Usage:
+    xsv histogram [options] [<input>]
+    xsv histogram --help
+
+histogram options:
+    -s, --select <arg>     Select a subset of columns to compute histograms
+                           for. See 'xsv select --help' for the format
+                           details.
+    -b, --bins <arg>       The number of bins. [default: 10]
+    -q, --quantiles        Split the bins at quantiles instead of at equal
+                           widths.
+    -e, --edges <list>     The edges of the bins. This overrides --bins.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will NOT be interpreted
+                           as column names. Additionally, the 'field'
+                           column will be 1-based indices instead of header
+                           names.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_select: SelectColumns,
+    flag_bins: usize,
+    flag_quantiles: bool,
+    flag_edges: Option<Edges>,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_bins == 0 {
+        return fail!("--bins must be greater than 0.");
+    }
+    if args.flag_quantiles && args.flag_edges.is_some() {
+        return fail!("--quantiles and --edges can't be used together.");
+    }
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers)
+        .select(args.flag_select.clone());
+
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+    let mut columns: Vec<Column> =
+        sel.iter().map(|_| Column::default()).collect();
+    for record in rdr.byte_records() {
+        let record = record?;
+        for (column, field) in columns.iter_mut().zip(sel.select(&record)) {
+            column.add(field);
+        }
+    }
+
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    wtr.write_record(vec!["field", "lower", "upper", "count"])?;
+    for (&i, column) in sel.iter().zip(columns) {
+        if !column.typ.is_number() {
+            continue;
+        }
+        let name =
+            if args.flag_no_headers {
+                (i + 1).to_string().into_bytes()
+            } else {
+                headers[i].to_vec()
+            };
+        let edges = match args.flag_edges {
+            Some(ref edges) => edges.0.clone(),
+            None if args.flag_quantiles => {
+                quantile_edges(&column.values, args.flag_bins)
+            }
+            None => equal_edges(&column.values, args.flag_bins),
+        };
+        for (bounds, count) in edges.windows(2).zip(count(&edges, &column)) {
+            let lower = bounds[0].to_string();
+            let upper = bounds[1].to_string();
+            let count = count.to_string();
+            wtr.write_record(vec![
+                &*name, lower.as_bytes(), upper.as_bytes(), count.as_bytes(),
+            ])?;
+        }
+    }
+    Ok(wtr.flush()?)
+}
+
+/// Column holds the type and the numeric values of one column.
+#[derive(Default)]
+struct Column {
+    typ: FieldType,
+    values: Vec<f64>,
+}
+
+impl Column {
+    fn add(&mut self, sample: &[u8]) {
+        let typ = FieldType::from_sample(sample);
+        self.typ.merge(typ);
+        // Once a column has a value that isn't a number, it won't be
+        // binned, so there's no need to keep its values.
+        if !self.typ.is_number() {
+            self.values.clear();
+            return;
+        }
+        if let Some(n) = str::from_utf8(sample).ok()
+                             .and_then(|s| s.parse::<f64>().ok()) {
+            if n.is_finite() {
+                self.values.push(n);
+            }
+        }
+    }
+}
+
+/// Split the range of `values` into `bins` bins of equal width.
+fn equal_edges(values: &[f64], bins: usize) -> Vec<f64> {
+    if values.is_empty() {
+        return vec![];
+    }
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if min == max {
+        return vec![min, max];
+    }
+    let mut edges: Vec<f64> = (0..bins).map(|i| {
+        min + (max - min) * i as f64 / bins as f64
+    }).collect();
+    edges.push(max);
+    edges
+}
+
+/// Split `values` at `bins - 1` evenly spaced quantiles, merging bins whose
+/// edges are the same.
+fn quantile_edges(values: &[f64], bins: usize) -> Vec<f64> {
+    let mut quantiles = Quantiles::Exact(values.to_vec());
+    let mut edges: Vec<f64> = vec![];
+    for i in 0..bins + 1 {
+        let q = match quantiles.quantile(i as f64 / bins as f64) {
+            None => break,
+            Some(q) => q,
+        };
+        if edges.last() != Some(&q) {
+            edges.push(q);
+        }
+    }
+    // When every value is the same, there's still one bin.
+    if edges.len() == 1 {
+        let edge = edges[0];
+        edges.push(edge);
+    }
+    edges
+}
+
+/// Count the values of a column in the bins given by `edges`.
+fn count(edges: &[f64], column: &Column) -> Vec<u64> {
+    let mut counts = vec![0; edges.len().saturating_sub(1)];
+    if counts.is_empty() {
+        return counts;
+    }
+    let last = edges.len() - 1;
+    for &x in &column.values {
+        if x < edges[0] || x > edges[last] {
+            continue;
+        }
+        let bin = match edges.binary_search_by(|e| {
+            e.partial_cmp(&x).unwrap_or(Ordering::Less)
+        }) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        counts[bin.min(last - 1)] += 1;
+    }
+    counts
+}
+
+/// Edges is an increasing list of at least two bin edges.
+#[derive(Clone, Debug)]
+struct Edges(Vec<f64>);
+
+impl<'de> Deserialize<'de> for Edges {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Edges, D::Error> {
+        let raw = String::deserialize(d)?;
+        let mut edges = vec![];
+        for edge in raw.split(',') {
+            match edge.trim().parse::<f64>() {
+                Ok(n) if n.is_finite() => edges.push(n),
+                _ => {
+                    let msg = format!("Invalid bin edge '{}'.", edge);
+                    return Err(D::Error::custom(msg));
+                }
+            }
+        }
+        if edges.len() < 2 || edges.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(D::Error::custom(
+                "Bin edges must be at least two increasing numbers."));
+        }
+        Ok(Edges(edges))
+    }
+}
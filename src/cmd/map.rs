@@ -0,0 +1,119 @@
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use expr::{Definition, Expr};
+use util;
+
+static USAGE: &'static str = "
+Adds or replaces columns using expressions evaluated on each row.
+
+The expression is a list of column definitions separated by semicolons, each
+of the form 'name = expression'. For example, this adds a 'total' column and
+replaces the 'date' column with just its year:
+
+    xsv map 'total = qty * price; date = substr(date, 0, 4)' data.csv
+
+If a definition names an existing column (using the same syntax as a column
+reference in an expression), then that column is replaced in place.
+Otherwise, a new column with that name is appended to each row. Definitions
+are evaluated in order, so later definitions can refer to columns created or
+replaced by earlier ones.
+
+A field that is copied or joined with other text keeps its text exactly,
+e.g., 'b = a' copies '007' and '1.50' unchanged. Only the result of
+arithmetic or of a numeric function is formatted as a new number.
+
+Expressions use the same syntax as 'xsv filter'; see 'xsv filter --help' for
+details on column references, types and operators. Along with the functions
+listed there, the following are available:
+
+    substr(s, start[, len]) The characters of s from start (0-based) and, if
+                            given, at most len of them. A negative start
+                            counts back from the end of s.
+    concat(x, ...)          Join its arguments as text. Nulls are treated as
+                            empty strings.
+    replace(s, from, to)    Replace every occurrence of from in s with to.
+    if(cond, a[, b])        a if cond is true and b (or null) otherwise.
+    coalesce(x, ...)        The first argument that isn't null.
+    abs(n)                  The absolute value of n.
+    round(n[, digits])      Round n to the given number of decimal places.
+    floor(n), ceil(n)       Round n down or up to an integer.
+
+Unless noted otherwise, a function returns null (an empty field) when any of
+its arguments are null.
+
+Usage:
+    xsv map [options] <expression> [<input>]
+    xsv map --help
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. Columns must then be referred to by
+                           index, and new columns are only appended.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    arg_expression: String,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+/// Column is a compiled definition along with the field it writes to.
+struct Column {
+    expr: Expr,
+    index: usize,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+
+    // Compile each definition against the headers produced by the ones
+    // before it, so that definitions can build on each other.
+    let use_names = !rconfig.no_headers;
+    let mut headers = rdr.byte_headers()?.clone();
+    let mut columns = vec![];
+    for def in Definition::parse_all(&args.arg_expression)? {
+        let expr = def.compile(&headers, use_names)?;
+        let index = match def.target(&headers, use_names)? {
+            Some(i) => i,
+            None => {
+                headers.push_field(def.name.as_bytes());
+                headers.len() - 1
+            }
+        };
+        columns.push(Column { expr: expr, index: index });
+    }
+
+    if !rconfig.no_headers {
+        wtr.write_byte_record(&headers)?;
+    }
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        for col in &columns {
+            let value = col.expr.eval(&record).to_string();
+            if col.index < record.len() {
+                record = record.iter().enumerate().map(|(i, field)| {
+                    if i == col.index { value.as_bytes() } else { field }
+                }).collect();
+            } else {
+                record.push_field(value.as_bytes());
+            }
+        }
+        wtr.write_byte_record(&record)?;
+    }
+    Ok(wtr.flush()?)
+}
@@ -0,0 +1,117 @@
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use select::SelectColumns;
+use util;
+
+static USAGE: &'static str = "
+Reshapes CSV data from wide to long format (the inverse of 'xsv pivot').
+
+Each row of the input is turned into one row for each of the value columns.
+Every output row has the id columns of the input row, followed by a
+'variable' column with the name of the value column and a 'value' column
+with its value. For example, with '--id name':
+
+    name,2017,2018            name,variable,value
+    alice,1,2         =>      alice,2017,1
+    bob,3,4                   alice,2018,2
+                              bob,2017,3
+                              bob,2018,4
+
+Both the id and value columns are selected using the syntax described in
+'xsv select --help'. By default, every column that isn't an id column is a
+value column.
+
+Usage:
+    xsv melt [options] [<input>]
+    xsv melt --help
+
+melt options:
+    -i, --id <arg>         The id columns, which are repeated on every row.
+    -s, --values <arg>     The value columns. By default, all columns that
+                           aren't id columns are used.
+    --variable-name <arg>  The name of the variable column.
+                           [default: variable]
+    --value-name <arg>     The name of the value column. [default: value]
+    --drop-empty           Don't write rows whose value is empty.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. Columns are then named by their
+                           index (starting at 1) in the variable column,
+                           and no header row is written.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_id: Option<SelectColumns>,
+    flag_values: Option<SelectColumns>,
+    flag_variable_name: String,
+    flag_value_name: String,
+    flag_drop_empty: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+
+    let headers = rdr.byte_headers()?.clone();
+    let use_names = !rconfig.no_headers;
+    let ids = match args.flag_id {
+        None => vec![],
+        Some(ref sel) => sel.selection(&headers, use_names)?.to_vec(),
+    };
+    let values = match args.flag_values {
+        Some(ref sel) => sel.selection(&headers, use_names)?.to_vec(),
+        None => (0..headers.len()).filter(|i| !ids.contains(i)).collect(),
+    };
+    let names: Vec<Vec<u8>> = values.iter().map(|&i| {
+        if use_names {
+            headers[i].to_vec()
+        } else {
+            (i + 1).to_string().into_bytes()
+        }
+    }).collect();
+
+    if use_names {
+        let mut row = csv::ByteRecord::new();
+        for &i in &ids {
+            row.push_field(&headers[i]);
+        }
+        row.push_field(args.flag_variable_name.as_bytes());
+        row.push_field(args.flag_value_name.as_bytes());
+        wtr.write_byte_record(&row)?;
+    }
+    let mut record = csv::ByteRecord::new();
+    let mut row = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        for (&i, name) in values.iter().zip(&names) {
+            let value = &record[i];
+            if args.flag_drop_empty && value.is_empty() {
+                continue;
+            }
+            row.clear();
+            for &j in &ids {
+                row.push_field(&record[j]);
+            }
+            row.push_field(name);
+            row.push_field(value);
+            wtr.write_byte_record(&row)?;
+        }
+    }
+    Ok(wtr.flush()?)
+}
@@ -1,14 +1,23 @@
 pub mod cat;
 pub mod count;
+pub mod dedup;
+pub mod filter;
 pub mod fixlengths;
 pub mod flatten;
 pub mod fmt;
 pub mod frequency;
+pub mod groupby;
 pub mod headers;
+pub mod histogram;
 pub mod index;
 pub mod input;
 pub mod join;
+pub mod map;
+pub mod melt;
 pub mod partition;
+pub mod pivot;
+pub mod rename;
+pub mod replace;
 pub mod reverse;
 pub mod sample;
 pub mod search;
@@ -18,3 +27,4 @@ pub mod sort;
 pub mod split;
 pub mod stats;
 pub mod table;
+pub mod transpose;
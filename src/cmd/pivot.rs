@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use select::SelectColumns;
+use util;
+
+static USAGE: &'static str = "
+Reshapes CSV data from long to wide format (the inverse of 'xsv melt').
+
+Each distinct value of <column> becomes a new column, filled in with the
+values of <value>. Rows are grouped by their id columns, so that there is one
+output row for each distinct combination of ids. For example:
+
+    name,year,sales                      name,2017,2018
+    alice,2017,1                         alice,1,2
+    alice,2018,2       =>                bob,3,
+    bob,2017,3
+
+    xsv pivot year sales data.csv
+
+<column> and <value> must each select a single column, using the syntax
+described in 'xsv select --help'. By default, the id columns are all of the
+other columns. New columns are added in the order their names first appear,
+and rows are written in the order their ids first appear. Cells with no
+value are left empty.
+
+When more than one row has the same ids and <column>, their values are
+combined with --agg, which is one of:
+
+    first, last            The first or last value.
+    count                  The number of values.
+    sum, mean              The sum or mean of the values.
+    min, max               The smallest or largest value.
+
+Values that aren't numbers are ignored by sum, mean, min and max.
+
+All of the output is kept in memory.
+
+Usage:
+    xsv pivot [options] <column> <value> [<input>]
+    xsv pivot --help
+
+pivot options:
+    -i, --id <arg>         The id columns. By default, all columns other than
+                           <column> and <value> are used.
+    -a, --agg <func>       How to combine values that land in the same cell.
+                           [default: first]
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    arg_column: SelectColumns,
+    arg_value: SelectColumns,
+    flag_id: Option<SelectColumns>,
+    flag_agg: Agg,
+    flag_output: Option<String>,
+    flag_delimiter: Option<Delimiter>,
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+enum Agg {
+    First,
+    Last,
+    Count,
+    Sum,
+    Mean,
+    Min,
+    Max,
+}
+
+/// Cell accumulates the values that land in one cell of the output.
+enum Cell {
+    Value(Vec<u8>),
+    Count(u64),
+    /// The sum and number of numeric values, for `sum` and `mean`.
+    Sum(f64, u64),
+    Extreme(Option<f64>),
+}
+
+impl Cell {
+    fn new(agg: Agg) -> Cell {
+        match agg {
+            Agg::First | Agg::Last => Cell::Value(vec![]),
+            Agg::Count => Cell::Count(0),
+            Agg::Sum | Agg::Mean => Cell::Sum(0.0, 0),
+            Agg::Min | Agg::Max => Cell::Extreme(None),
+        }
+    }
+
+    fn add(&mut self, agg: Agg, first: bool, value: &[u8]) {
+        let number = || {
+            ::std::str::from_utf8(value).ok()
+                .and_then(|s| s.parse::<f64>().ok())
+                .and_then(|n| if n.is_nan() { None } else { Some(n) })
+        };
+        match *self {
+            Cell::Value(ref mut v) => {
+                if first || agg == Agg::Last {
+                    *v = value.to_vec();
+                }
+            }
+            Cell::Count(ref mut n) => *n += 1,
+            Cell::Sum(ref mut sum, ref mut n) => {
+                if let Some(x) = number() {
+                    *sum += x;
+                    *n += 1;
+                }
+            }
+            Cell::Extreme(ref mut ext) => {
+                if let Some(x) = number() {
+                    *ext = Some(match *ext {
+                        None => x,
+                        Some(y) if agg == Agg::Min => x.min(y),
+                        Some(y) => x.max(y),
+                    });
+                }
+            }
+        }
+    }
+
+    fn value(&self, agg: Agg) -> Vec<u8> {
+        match *self {
+            Cell::Value(ref v) => v.clone(),
+            Cell::Count(n) => n.to_string().into_bytes(),
+            Cell::Sum(_, 0) => vec![],
+            Cell::Sum(sum, n) => {
+                let x = if agg == Agg::Mean { sum / n as f64 } else { sum };
+                x.to_string().into_bytes()
+            }
+            Cell::Extreme(None) => vec![],
+            Cell::Extreme(Some(x)) => x.to_string().into_bytes(),
+        }
+    }
+}
+
+/// A row of output: its ids and a cell for each new column seen so far.
+type Row = (Vec<Vec<u8>>, Vec<Option<Cell>>);
+
+/// Resolve a selection that must be exactly one column.
+fn one_column(
+    sel: &SelectColumns,
+    headers: &csv::ByteRecord,
+    what: &str,
+) -> CliResult<usize> {
+    let sel = sel.selection(headers, true)?;
+    if sel.len() != 1 {
+        return fail!(format!("{} must select exactly one column.", what));
+    }
+    Ok(sel[0])
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+
+    let headers = rdr.byte_headers()?.clone();
+    let column = one_column(&args.arg_column, &headers, "<column>")?;
+    let value = one_column(&args.arg_value, &headers, "<value>")?;
+    let ids = match args.flag_id {
+        Some(ref sel) => sel.selection(&headers, true)?.to_vec(),
+        None => {
+            (0..headers.len())
+                .filter(|&i| i != column && i != value)
+                .collect()
+        }
+    };
+
+    let agg = args.flag_agg;
+    let mut columns: Vec<Vec<u8>> = vec![];
+    let mut column_index: HashMap<Vec<u8>, usize> = HashMap::new();
+    let mut rows: Vec<Row> = vec![];
+    let mut row_index: HashMap<Vec<Vec<u8>>, usize> = HashMap::new();
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        let name = &record[column];
+        let ci = match column_index.get(name).cloned() {
+            Some(ci) => ci,
+            None => {
+                columns.push(name.to_vec());
+                column_index.insert(name.to_vec(), columns.len() - 1);
+                columns.len() - 1
+            }
+        };
+        let key: Vec<Vec<u8>> =
+            ids.iter().map(|&i| record[i].to_vec()).collect();
+        let ri = match row_index.get(&key).cloned() {
+            Some(ri) => ri,
+            None => {
+                rows.push((key.clone(), vec![]));
+                row_index.insert(key, rows.len() - 1);
+                rows.len() - 1
+            }
+        };
+        let cells = &mut rows[ri].1;
+        while cells.len() <= ci {
+            cells.push(None);
+        }
+        let first = cells[ci].is_none();
+        cells[ci].get_or_insert_with(|| Cell::new(agg))
+                 .add(agg, first, &record[value]);
+    }
+
+    let mut out = csv::ByteRecord::new();
+    for &i in &ids {
+        out.push_field(&headers[i]);
+    }
+    for name in &columns {
+        out.push_field(name);
+    }
+    wtr.write_byte_record(&out)?;
+    for (key, cells) in rows {
+        out.clear();
+        for field in &key {
+            out.push_field(field);
+        }
+        for ci in 0..columns.len() {
+            match cells.get(ci).and_then(|c| c.as_ref()) {
+                Some(cell) => out.push_field(&cell.value(agg)),
+                None => out.push_field(b""),
+            }
+        }
+        wtr.write_byte_record(&out)?;
+    }
+    Ok(wtr.flush()?)
+}
@@ -0,0 +1,228 @@
+use csv;
+use regex::bytes::Regex;
+
+use CliResult;
+use config::{Config, Delimiter};
+use select::SelectColumns;
+use util;
+
+static USAGE: &'static str = "
+Renames the columns of CSV data.
+
+By default, <names> is a list of new names (in CSV format) for all of the
+columns, in order. If --select is given, then only the selected columns are
+renamed, and <names> must have one name for each of them:
+
+    xsv rename 'id,first_name,last_name' data.csv
+    xsv rename -s 2-3 'first_name,last_name' data.csv
+
+With --pairs, <names> is instead a list of 'old:new' pairs, where each old
+name is a single column in the same syntax as 'xsv select':
+
+    xsv rename --pairs 'FName:first_name,3:last_name' data.csv
+
+Quote an old name with double quotes if it contains a ',' or a ':' (or
+would otherwise be parsed as a range), e.g., '\"a-b\":ab'.
+
+With --regex, every match of the regex in the names of the selected columns
+is replaced with <names>, which can refer to capture groups (e.g., '$1'):
+
+    xsv rename --regex '^col_(.*)$' '$1' data.csv
+
+Finally, --normalize cleans up the names of the selected columns, after any
+other renaming. It takes a comma separated list of modes, applied in order:
+'trim' removes leading and trailing whitespace, 'lower' converts to
+lowercase and 'snake' converts to snake_case (e.g., 'Unit Price' and
+'unitPrice' both become 'unit_price').
+
+    xsv rename --normalize snake data.csv
+
+Usage:
+    xsv rename [options] --normalize <modes> [<input>]
+    xsv rename [options] <names> [<input>]
+    xsv rename --help
+
+rename options:
+    -s, --select <arg>     Select the columns to rename. See 'xsv select -h'
+                           for the full syntax. This does not apply to
+                           --pairs.
+    -p, --pairs            Treat <names> as a list of 'old:new' pairs.
+    -r, --regex <regex>    Replace matches of <regex> in the names of the
+                           selected columns with <names>.
+    --normalize <modes>    Normalize the names of the selected columns.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. The new names are then written as a
+                           header row before it. Only a list of names can be
+                           given in this case.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    arg_names: Option<String>,
+    flag_select: SelectColumns,
+    flag_pairs: bool,
+    flag_regex: Option<String>,
+    flag_normalize: Option<String>,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+#[derive(Clone, Copy)]
+enum Normalize {
+    Trim,
+    Lower,
+    Snake,
+}
+
+impl Normalize {
+    fn parse_all(modes: &str) -> Result<Vec<Normalize>, String> {
+        modes.split(',').map(|mode| match mode.trim() {
+            "trim" => Ok(Normalize::Trim),
+            "lower" => Ok(Normalize::Lower),
+            "snake" => Ok(Normalize::Snake),
+            mode => Err(format!(
+                "Unknown normalization mode '{}'. Expected one of trim, \
+                 lower or snake.", mode)),
+        }).collect()
+    }
+
+    fn apply(self, name: &str) -> String {
+        match self {
+            Normalize::Trim => name.trim().to_owned(),
+            Normalize::Lower => name.to_lowercase(),
+            Normalize::Snake => snake_case(name),
+        }
+    }
+}
+
+/// Convert `name` to snake_case.
+///
+/// Runs of characters that aren't letters or digits become a single
+/// underscore, and an underscore is inserted where a lowercase letter or
+/// digit is followed by an uppercase letter.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let mut prev: Option<char> = None;
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            if !snake.is_empty() && !snake.ends_with('_') {
+                snake.push('_');
+            }
+        } else {
+            let boundary = prev.map_or(false, |p| {
+                p.is_lowercase() || p.is_numeric()
+            });
+            if c.is_uppercase() && boundary {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        }
+        prev = Some(c);
+    }
+    if snake.ends_with('_') {
+        snake.pop();
+    }
+    snake
+}
+
+/// Remove the double quotes around `s`, if there are any.
+fn unquote(s: &str) -> String {
+    if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') {
+        s[1..s.len() - 1].replace("\"\"", "\"")
+    } else {
+        s.to_owned()
+    }
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers)
+        .select(args.flag_select.clone());
+    if rconfig.no_headers && (args.flag_pairs || args.flag_regex.is_some()
+                              || args.flag_normalize.is_some()) {
+        return fail!("Only a list of names can be given with --no-headers.");
+    }
+    if args.flag_pairs && args.flag_regex.is_some() {
+        return fail!("--pairs and --regex cannot be used together.");
+    }
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+
+    let headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+    let mut names: Vec<Vec<u8>> = headers.iter().map(|h| h.to_vec()).collect();
+    if let Some(ref new_names) = args.arg_names {
+        if let Some(ref regex) = args.flag_regex {
+            let regex = Regex::new(regex)?;
+            for &i in sel.iter() {
+                names[i] = regex.replace_all(&names[i], new_names.as_bytes())
+                                .into_owned();
+            }
+        } else if args.flag_pairs {
+            for pair in util::split_unquoted(new_names, ',') {
+                let (i, new) = resolve_pair(pair, &headers)?;
+                names[i] = new.into_bytes();
+            }
+        } else {
+            let new_names = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .from_reader(new_names.as_bytes())
+                .byte_records()
+                .next()
+                .unwrap_or_else(|| Ok(csv::ByteRecord::new()))?;
+            if new_names.len() != sel.len() {
+                return fail!(format!(
+                    "Got {} new names, but {} columns are being renamed.",
+                    new_names.len(), sel.len()));
+            }
+            for (&i, name) in sel.iter().zip(new_names.iter()) {
+                names[i] = name.to_vec();
+            }
+        }
+    }
+    if let Some(ref modes) = args.flag_normalize {
+        for mode in Normalize::parse_all(modes)? {
+            for &i in sel.iter() {
+                let name = mode.apply(&String::from_utf8_lossy(&names[i]));
+                names[i] = name.into_bytes();
+            }
+        }
+    }
+
+    wtr.write_record(&names)?;
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        wtr.write_byte_record(&record)?;
+    }
+    Ok(wtr.flush()?)
+}
+
+/// Parse an `old:new` pair into the index of the old column and its new
+/// name.
+fn resolve_pair(
+    pair: &str,
+    headers: &csv::ByteRecord,
+) -> CliResult<(usize, String)> {
+    let pieces = util::split_unquoted(pair, ':');
+    if pieces.len() != 2 {
+        return fail!(format!(
+            "Invalid pair '{}'. Pairs must have the form 'old:new'.", pair));
+    }
+    let sel = SelectColumns::parse(pieces[0])?.selection(headers, true)?;
+    if sel.len() != 1 {
+        return fail!(format!(
+            "'{}' must select exactly one column.", pieces[0]));
+    }
+    Ok((sel[0], unquote(pieces[1])))
+}
@@ -0,0 +1,115 @@
+use csv;
+use regex::bytes::{Captures, RegexBuilder};
+
+use CliResult;
+use config::{Config, Delimiter};
+use select::SelectColumns;
+use util;
+
+static USAGE: &'static str = "
+Replaces matches of a regex in CSV data.
+
+The regex is applied to each field in each row, and every match is replaced
+with <replacement>. The columns to modify can be limited with the '--select'
+flag. Headers are never modified. For example, this turns dates like
+'03/01/2018' into '2018-03-01':
+
+    xsv replace -s date '(\\d\\d)/(\\d\\d)/(\\d{4})' '$3-$1-$2' data.csv
+
+The replacement can refer to capture groups by index (e.g., '$1') or by name
+(e.g., '$name'). Use '${1}' to separate a reference from text that follows
+it and '$$' for a literal '$'.
+
+The number of substitutions made is written to stderr once all of the data has
+been processed.
+
+Usage:
+    xsv replace [options] <regex> <replacement> [<input>]
+    xsv replace --help
+
+replace options:
+    -i, --ignore-case      Case insensitive search. This is equivalent to
+                           prefixing the regex with '(?i)'.
+    -s, --select <arg>     Select the columns to modify. See 'xsv select -h'
+                           for the full syntax.
+    --first                Only replace the first match in each field.
+    -q, --quiet            Don't report the number of substitutions.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., It is modified like any other
+                           row.)
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    arg_regex: String,
+    arg_replacement: String,
+    flag_select: SelectColumns,
+    flag_ignore_case: bool,
+    flag_first: bool,
+    flag_quiet: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let pattern = RegexBuilder::new(&*args.arg_regex)
+        .case_insensitive(args.flag_ignore_case)
+        .build()?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers)
+        .select(args.flag_select);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+
+    let headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+    let mut selected = vec![false; headers.len()];
+    for &i in sel.iter() {
+        selected[i] = true;
+    }
+
+    rconfig.write_headers(&mut rdr, &mut wtr)?;
+    let limit = if args.flag_first { 1 } else { 0 };
+    // Expand the replacement ourselves so that we can count the
+    // substitutions.
+    let mut count = 0u64;
+    let replacement = args.arg_replacement.as_bytes();
+    {
+        let mut expand = |caps: &Captures| {
+            count += 1;
+            let mut expanded = vec![];
+            caps.expand(replacement, &mut expanded);
+            expanded
+        };
+        let mut record = csv::ByteRecord::new();
+        let mut replaced = csv::ByteRecord::new();
+        while rdr.read_byte_record(&mut record)? {
+            replaced.clear();
+            for (i, field) in record.iter().enumerate() {
+                if selected.get(i).cloned().unwrap_or(false) {
+                    replaced.push_field(
+                        &pattern.replacen(field, limit, &mut expand));
+                } else {
+                    replaced.push_field(field);
+                }
+            }
+            wtr.write_byte_record(&replaced)?;
+        }
+    }
+    wtr.flush()?;
+    if !args.flag_quiet {
+        werr!("{} substitution(s) made.", count);
+    }
+    Ok(())
+}
@@ -1,10 +1,23 @@
+use std::cmp::{self, Ordering};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::str;
+use std::sync::Arc;
+
+use aho_corasick::{AcAutomaton, Automaton};
+use channel;
+
 use csv;
-use regex::bytes::RegexBuilder;
+use regex::bytes::{RegexBuilder, RegexSet, RegexSetBuilder};
 use regex::bytes::Regex;
 
 use CliResult;
 use config::{Config, Delimiter};
-use select::SelectColumns;
+use date::DateTime;
+use index::Indexed;
+use select::{SelectColumns, Selection};
+use threadpool::ThreadPool;
 use util;
 
 static USAGE: &'static str = "
@@ -15,7 +28,56 @@ then the row is written to the output. The columns to search can be limited
 with the '--select' flag (but the full row is still written to the output if
 there is a match).
 
+Different patterns can be given for different columns with --pattern, which
+may be repeated. Each pattern has the form '<column>=~<regex>' (the column
+must match) or '<column>!~<regex>' (the column must not match), where the
+column uses the same syntax as 'xsv select'. A column name that contains an
+operator can be quoted, e.g., '\"a<b\"=~x'. If it selects more than one
+column, then the pattern matches when any of them match. By default, a row is
+written when all of the patterns match. For example:
+
+    xsv search -e 'name=~^A' -e 'email=~@corp\\.com$' data.csv
+
+Instead of a regex, a pattern can compare a column against a bound using one
+of the operators >=, >, <= or <. Together, these can select a range:
+
+    xsv search --type number -e 'amount>=10' -e 'amount<100' data.csv
+
+The --greater-than and --less-than flags do the same for the columns
+selected with --select, with <regex> used as the bound.
+
+Comparisons are done according to --type. 'text' (the default) compares the
+raw bytes of each field, so that '9' is greater than '10'. 'number' compares
+fields as numbers and 'date' compares them as ISO 8601 dates or date-times
+(e.g., '2018-03-01' or '2018-03-01T12:30:00+02:00'), converting any UTC
+offsets. A date without a time is treated as midnight. Empty fields never
+match a number or date comparison. Other fields that can't be parsed as the
+given type are handled according to --unparseable: 'skip' treats them as not
+matching, 'match' treats them as matching and 'error' stops with an error.
+
+To search for many patterns at once, put them in a file, one per line, and
+use --patterns-file. All of the patterns are matched together in a single
+pass, and a row matches if any pattern matches any of the selected fields.
+Blank lines in the file are ignored. With --match-column, a column is added
+to the output that shows the first pattern (in file order) that matched.
+With --literal, the lines are matched as plain strings using the
+Aho-Corasick algorithm, which stays fast for lists of many thousands of
+strings. Combined with --ignore-case, literal strings and fields are
+compared in lower case.
+
+Instead of writing the matching rows, --count writes only the number of them
+and --flag writes every row, with a column that says whether it matched.
+With --row-numbers, a 'row' column is added to the start of each row with its
+position in the input, where the first record (after the header) is 1.
+
+When the input has an index, --jobs can be used to search it in parallel.
+The input is split into chunks of at most 10,000 rows, and rows are still
+written in their original order. Only a few chunks are searched ahead of the
+last one written, so memory use doesn't grow with the size of the input.
+
 Usage:
+    xsv search [options] (-e <arg>)... [<input>]
+    xsv search [options] --patterns-file <file> [<input>]
     xsv search [options] <regex> [<input>]
     xsv search --help
 
@@ -25,10 +87,37 @@ search options:
     -s, --select <arg>          Select the columns to search. See 'xsv select -h'
                                 for the full syntax.
     -v, --invert-match          Select only rows that did not match
-    -g, --greater-than          Filter to rows with fields lexigraphically greater
-                                than or equal to the argument.
-    -l, --less-than             Filter to rows with fields lexigraphically less
-                                than or equal to the argument.
+    -e, --pattern <arg>         A per-column pattern, as described above.
+                                Can be given many times.
+    --any                       When using --pattern, select rows for which
+                                any pattern matches instead of all of them.
+    -f, --patterns-file <file>  Search for every regex in <file>, one per line.
+    --literal                   Treat each line of the patterns file as a
+                                literal string instead of a regex.
+    -m, --match-column <name>   Add a column named <name> with the pattern
+                                from the patterns file that matched each row.
+    -c, --count                 Write the number of matching rows instead of
+                                the rows themselves.
+    --flag <column>             Write every row, adding a column named <column>
+                                that is true for matching rows and false
+                                otherwise.
+    --row-numbers               Add a column with the position of each row in
+                                the input.
+    -j, --jobs <arg>            The number of jobs to run in parallel when
+                                the input is indexed. When set to '0', the
+                                number of jobs is set to the number of CPUs
+                                detected. [default: 1]
+    -g, --greater-than          Filter to rows with fields greater than or
+                                equal to the argument.
+    -l, --less-than             Filter to rows with fields less than or equal
+                                to the argument.
+    --exclusive                 Exclude the bound itself from the range given
+                                by --greater-than or --less-than.
+    --type <arg>                How to compare fields against bounds: one of
+                                text, number or date. [default: text]
+    --unparseable <arg>         What to do with fields that can't be compared
+                                with --type: one of skip, match or error.
+                                [default: skip]
 
 Common options:
     -h, --help             Display this message
@@ -40,11 +129,20 @@ Common options:
                            Must be a single character. (default: ,)
 ";
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 struct Args {
     arg_input: Option<String>,
     arg_regex: String,
     flag_select: SelectColumns,
+    flag_pattern: Vec<String>,
+    flag_any: bool,
+    flag_patterns_file: Option<String>,
+    flag_literal: bool,
+    flag_match_column: Option<String>,
+    flag_count: bool,
+    flag_flag: Option<String>,
+    flag_row_numbers: bool,
+    flag_jobs: usize,
     flag_output: Option<String>,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
@@ -52,60 +150,536 @@ struct Args {
     flag_ignore_case: bool,
     flag_greater_than: bool,
     flag_less_than: bool,
+    flag_exclusive: bool,
+    flag_type: CompareType,
+    flag_unparseable: Unparseable,
 }
 
-enum Filter<'a>{
-    Eq(Regex),
-    Leq(&'a [u8]),
-    Geq(&'a [u8]),
+#[derive(Clone, Copy, Deserialize)]
+enum CompareType {
+    Text,
+    Number,
+    Date,
 }
 
-impl<'a> Filter<'a> {
-    fn apply(&self, field: &[u8]) -> bool {
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+enum Unparseable {
+    Skip,
+    Match,
+    Error,
+}
+
+/// Value is a field (or bound) parsed according to a `CompareType`.
+#[derive(PartialEq, PartialOrd)]
+enum Value<'a> {
+    Text(&'a [u8]),
+    Number(f64),
+    Date(DateTime),
+}
+
+impl CompareType {
+    fn name(self) -> &'static str {
+        match self {
+            CompareType::Text => "text",
+            CompareType::Number => "a number",
+            CompareType::Date => "a date",
+        }
+    }
+
+    fn parse<'a>(self, field: &'a [u8]) -> Option<Value<'a>> {
         match self {
-            Filter::Eq(pattern) => pattern.is_match(field),
-            Filter::Leq(bound) => field <= bound,
-            Filter::Geq(bound) => field >= bound,
+            CompareType::Text => Some(Value::Text(field)),
+            CompareType::Number => {
+                str::from_utf8(field).ok()
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .and_then(|n| if n.is_nan() { None } else { Some(n) })
+                    .map(Value::Number)
+            }
+            CompareType::Date => DateTime::parse(field).map(Value::Date),
+        }
+    }
+}
+
+enum Filter {
+    Eq(Regex),
+    /// Compare fields to a bound, matching if the ordering of the field
+    /// relative to the bound is one of the given orderings.
+    Cmp(CompareType, Vec<u8>, &'static [Ordering]),
+    /// Match any of many patterns at once. The original patterns are kept
+    /// for reporting which one matched.
+    Set(RegexSet, Vec<String>),
+    /// Like `Set`, but for literal strings. When the flag is set, the
+    /// strings in the automaton are in lower case, and so is each field
+    /// before it is searched.
+    Literals(AcAutomaton<String>, Vec<String>, bool),
+}
+
+impl Filter {
+    /// Returns `None` if the field couldn't be parsed for a comparison.
+    fn apply(&self, field: &[u8]) -> Option<bool> {
+        match *self {
+            Filter::Eq(ref pattern) => Some(pattern.is_match(field)),
+            Filter::Set(ref set, _) => Some(set.is_match(field)),
+            Filter::Literals(ref aut, _, false) => {
+                Some(aut.find(field).next().is_some())
+            }
+            Filter::Literals(ref aut, _, true) => {
+                Some(aut.find(&lowercase(field)).next().is_some())
+            }
+            Filter::Cmp(CompareType::Number, _, _)
+            | Filter::Cmp(CompareType::Date, _, _) if field.is_empty() => {
+                Some(false)
+            }
+            Filter::Cmp(typ, ref bound, ords) => {
+                let field = typ.parse(field)?;
+                // The bound was checked when the filter was created.
+                let bound = typ.parse(bound).unwrap();
+                match field.partial_cmp(&bound) {
+                    Some(o) => Some(ords.contains(&o)),
+                    None => Some(false),
+                }
+            }
         }
     }
+
+    /// The index of the first pattern (in file order) of a pattern set that
+    /// matches `field`.
+    fn first_match(&self, field: &[u8]) -> Option<usize> {
+        match *self {
+            Filter::Set(ref set, _) => set.matches(field).iter().next(),
+            Filter::Literals(ref aut, _, false) => {
+                aut.find_overlapping(field).map(|m| m.pati).min()
+            }
+            Filter::Literals(ref aut, _, true) => {
+                let field = lowercase(field);
+                aut.find_overlapping(&field).map(|m| m.pati).min()
+            }
+            Filter::Eq(_) | Filter::Cmp(_, _, _) => None,
+        }
+    }
+}
+
+/// Convert a field to lower case, for case insensitive literal matching.
+fn lowercase(field: &[u8]) -> Vec<u8> {
+    match str::from_utf8(field) {
+        Ok(s) => s.to_lowercase().into_bytes(),
+        Err(_) => field.to_ascii_lowercase(),
+    }
+}
+
+/// ColumnFilter applies a filter to some columns of a row.
+///
+/// It matches when the filter matches any of the selected fields (or, when
+/// `negate` is set, when it matches none of them).
+struct ColumnFilter {
+    sel: Selection,
+    filter: Filter,
+    negate: bool,
+}
+
+impl ColumnFilter {
+    fn apply(
+        &self,
+        record: &csv::ByteRecord,
+        unparseable: Unparseable,
+    ) -> CliResult<bool> {
+        for field in self.sel.select(record) {
+            let m = match self.filter.apply(field) {
+                Some(m) => m,
+                None if unparseable == Unparseable::Error => {
+                    let typ = match self.filter {
+                        Filter::Cmp(typ, _, _) => typ,
+                        _ => unreachable!(),
+                    };
+                    return fail!(format!(
+                        "Could not parse field '{}' as {}{}.",
+                        String::from_utf8_lossy(field), typ.name(),
+                        record.position().map_or(String::new(), |p| {
+                            format!(" on line {}", p.line())
+                        })));
+                }
+                None => unparseable == Unparseable::Match,
+            };
+            if m {
+                return Ok(!self.negate);
+            }
+        }
+        Ok(self.negate)
+    }
+
+    /// The first pattern of a pattern set that matches any selected field.
+    fn set_match(&self, record: &csv::ByteRecord) -> Option<&str> {
+        let patterns = match self.filter {
+            Filter::Set(_, ref patterns)
+            | Filter::Literals(_, ref patterns, _) => patterns,
+            _ => return None,
+        };
+        self.sel.select(record)
+            .filter_map(|f| self.filter.first_match(f))
+            .min()
+            .map(|i| &*patterns[i])
+    }
+}
+
+/// The size limit, in bytes, of the compiled patterns from a patterns file
+/// and of the cache used to match them.
+const PATTERN_SET_SIZE_LIMIT: usize = 256 << 20;
+
+/// Read the patterns file and build a set that matches all of them.
+fn pattern_set(path: &str, args: &Args) -> CliResult<Filter> {
+    let mut contents = String::new();
+    fs::File::open(path)?.read_to_string(&mut contents)?;
+    let patterns: Vec<String> = contents.lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_owned())
+        .collect();
+    if patterns.is_empty() {
+        return fail!(format!("No patterns found in '{}'.", path));
+    }
+    if args.flag_literal {
+        let icase = args.flag_ignore_case;
+        let aut = AcAutomaton::new(patterns.iter().map(|p| {
+            if icase { p.to_lowercase() } else { p.clone() }
+        }));
+        return Ok(Filter::Literals(aut, patterns, icase));
+    }
+    let mut builder = RegexSetBuilder::new(&patterns);
+    // The default limits are meant for single regexes, and are too small
+    // for a set of thousands of patterns.
+    builder.case_insensitive(args.flag_ignore_case)
+        .size_limit(PATTERN_SET_SIZE_LIMIT)
+        .dfa_size_limit(PATTERN_SET_SIZE_LIMIT);
+    let set = builder.build()
+        .map_err(|err| format!(
+            "Could not compile the patterns in '{}': {}", path, err))?;
+    Ok(Filter::Set(set, patterns))
+}
+
+static PATTERN_OPS: &'static [&'static str] = &[
+    "=~", "!~", ">=", "<=", ">", "<",
+];
+
+/// Create a comparison filter, making sure that its bound can be parsed.
+fn comparison(
+    typ: CompareType,
+    bound: &str,
+    ords: &'static [Ordering],
+) -> CliResult<Filter> {
+    if bound.is_empty() || typ.parse(bound.as_bytes()).is_none() {
+        return fail!(format!(
+            "Could not parse bound '{}' as {}.", bound, typ.name()));
+    }
+    Ok(Filter::Cmp(typ, bound.as_bytes().to_vec(), ords))
 }
 
+/// Parse a per-column pattern like `<column>=~<regex>` or `<column>>=<bound>`
+/// into a column filter.
+fn parse_pattern(
+    spec: &str,
+    headers: &csv::ByteRecord,
+    args: &Args,
+) -> CliResult<ColumnFilter> {
+    let (pos, op) = match find_op(spec) {
+        Some(found) => found,
+        None => {
+            return fail!(format!(
+                "Invalid pattern '{}'. Patterns must have the form \
+                 '<column><op><value>', where <op> is one of {}.",
+                spec, PATTERN_OPS.join(" ")));
+        }
+    };
+    let sel = SelectColumns::parse(&spec[..pos])?
+        .selection(headers, !args.flag_no_headers)?;
+    let value = &spec[pos + op.len()..];
+    let filter = match op {
+        "=~" | "!~" => {
+            Filter::Eq(RegexBuilder::new(value)
+                .case_insensitive(args.flag_ignore_case)
+                .build()?)
+        }
+        ">=" => comparison(args.flag_type, value, &GEQ)?,
+        "<=" => comparison(args.flag_type, value, &LEQ)?,
+        ">" => comparison(args.flag_type, value, &GT)?,
+        _ => comparison(args.flag_type, value, &LT)?,
+    };
+    Ok(ColumnFilter { sel: sel, filter: filter, negate: op == "!~" })
+}
+
+/// Find the first operator in `spec` that isn't inside double quotes, so
+/// that a quoted column name may contain one.
+///
+/// At the same position, the longer operators come first in `PATTERN_OPS`,
+/// so `>=` is preferred to `>`.
+fn find_op(spec: &str) -> Option<(usize, &'static str)> {
+    let mut quoted = false;
+    for (i, c) in spec.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if !quoted {
+            let op = PATTERN_OPS.iter().find(|op| spec[i..].starts_with(**op));
+            if let Some(&op) = op {
+                return Some((i, op));
+            }
+        }
+    }
+    None
+}
+
+/// The largest number of rows in a chunk searched by one job.
+const SEARCH_CHUNK_SIZE: usize = 10_000;
+
+/// The number of chunks per job that may be searched ahead of the last
+/// chunk written.
+const SEARCH_WINDOW: usize = 2;
+
+static GEQ: [Ordering; 2] = [Ordering::Greater, Ordering::Equal];
+static LEQ: [Ordering; 2] = [Ordering::Less, Ordering::Equal];
+static GT: [Ordering; 1] = [Ordering::Greater];
+static LT: [Ordering; 1] = [Ordering::Less];
+
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
-    let pattern = RegexBuilder::new(&*args.arg_regex)
-        .case_insensitive(args.flag_ignore_case)
-        .build()?;
-    let rconfig = Config::new(&args.arg_input)
-        .delimiter(args.flag_delimiter)
-        .no_headers(args.flag_no_headers)
-        .select(args.flag_select);
-
+    if (!args.flag_pattern.is_empty() || args.flag_patterns_file.is_some())
+        && (args.flag_greater_than || args.flag_less_than) {
+        return fail!("--greater-than and --less-than cannot be used with \
+                      --pattern or --patterns-file.");
+    }
+    if args.flag_match_column.is_some() && args.flag_patterns_file.is_none() {
+        return fail!("--match-column requires --patterns-file.");
+    }
+    if args.flag_count && (args.flag_flag.is_some() || args.flag_row_numbers
+                           || args.flag_match_column.is_some()) {
+        return fail!("--count cannot be used with --flag, --row-numbers or \
+                      --match-column.");
+    }
+    if args.flag_greater_than && args.flag_less_than {
+        return fail!("--greater-than and --less-than cannot be used \
+                      together. Use --pattern to give both bounds.");
+    }
+    let rconfig = args.rconfig();
     let mut rdr = rconfig.reader()?;
     let mut wtr = Config::new(&args.flag_output).writer()?;
 
     let headers = rdr.byte_headers()?.clone();
     let sel = rconfig.selection(&headers)?;
-
-    if !rconfig.no_headers {
-        wtr.write_record(&headers)?;
+    let searcher = Searcher::new(&args, &headers, sel)?;
+    if !rconfig.no_headers && !args.flag_count {
+        let mut row = csv::ByteRecord::new();
+        if args.flag_row_numbers {
+            row.push_field(b"row");
+        }
+        row.extend(&headers);
+        if let Some(ref name) = args.flag_match_column {
+            row.push_field(name.as_bytes());
+        }
+        if let Some(ref name) = args.flag_flag {
+            row.push_field(name.as_bytes());
+        }
+        wtr.write_byte_record(&row)?;
     }
-    let mut record = csv::ByteRecord::new();
 
-    let filter  = match (args.flag_greater_than, args.flag_less_than) {
-        (false, false) => Filter::Eq(pattern),
-        (false, true) => Filter::Leq(args.arg_regex.as_bytes()),
-        (true, false) => Filter::Geq(args.arg_regex.as_bytes()),
-        (true, true) => Filter::Eq(pattern)
+    let count = match rconfig.indexed()? {
+        Some(ref mut idx) if args.njobs() > 1 => {
+            args.parallel_search(idx, Arc::new(searcher), &mut wtr)?
+        }
+        _ => {
+            searcher.search(rdr.byte_records(), 1, |row| {
+                Ok(wtr.write_byte_record(&row)?)
+            })?
+        }
     };
+    if args.flag_count {
+        wtr.write_record(&[count.to_string()])?;
+    }
+    Ok(wtr.flush()?)
+}
+
+impl Args {
+    fn rconfig(&self) -> Config {
+        Config::new(&self.arg_input)
+            .delimiter(self.flag_delimiter)
+            .no_headers(self.flag_no_headers)
+            .select(self.flag_select.clone())
+    }
+
+    fn njobs(&self) -> usize {
+        if self.flag_jobs == 0 { util::num_cpus() } else { self.flag_jobs }
+    }
 
-    while rdr.read_byte_record(&mut record)? {
-        let mut m = sel.select(&record).any(|f| filter.apply(f));
-        if args.flag_invert_match {
-            m = !m;
+    /// Search chunks of an indexed file in parallel, writing the output of
+    /// each chunk in order. Returns the number of matching rows.
+    ///
+    /// At most `SEARCH_WINDOW` chunks per job are searched or held in
+    /// memory at once. A new chunk is started only when the oldest one has
+    /// been written.
+    fn parallel_search<W: io::Write>(
+        &self,
+        idx: &mut Indexed<fs::File, fs::File>,
+        searcher: Arc<Searcher>,
+        wtr: &mut csv::Writer<W>,
+    ) -> CliResult<u64> {
+        if idx.count() == 0 {
+            return Ok(0);
         }
-        if m {
-            wtr.write_byte_record(&record)?;
+        let chunk_size = cmp::min(
+            util::chunk_size(idx.count() as usize, self.njobs()),
+            SEARCH_CHUNK_SIZE);
+        let nchunks = util::num_of_chunks(idx.count() as usize, chunk_size);
+        let window = self.njobs() * SEARCH_WINDOW;
+
+        let pool = ThreadPool::new(self.njobs());
+        let (send, recv) = channel::bounded(0);
+        let start_chunk = |i: usize| {
+            let (send, args, searcher) =
+                (send.clone(), self.clone(), searcher.clone());
+            pool.execute(move || {
+                let start = (i * chunk_size) as u64;
+                let mut idx = args.rconfig().indexed().unwrap().unwrap();
+                idx.seek(start).unwrap();
+                let it = idx.byte_records().take(chunk_size);
+                let mut rows = vec![];
+                let result = searcher.search(it, start + 1, |row| {
+                    rows.push(row);
+                    Ok(())
+                });
+                send.send((i, result.map(|count| (count, rows))));
+            });
+        };
+        for i in 0..cmp::min(window, nchunks) {
+            start_chunk(i);
+        }
+
+        // Chunks can finish in any order, so hold on to each one until all
+        // of the chunks before it have been written.
+        let mut done: HashMap<usize, (u64, Vec<csv::ByteRecord>)> =
+            HashMap::new();
+        let (mut next, mut count) = (0, 0);
+        while next < nchunks {
+            let (i, result) = recv.recv().unwrap();
+            done.insert(i, result?);
+            while let Some((n, rows)) = done.remove(&next) {
+                for row in rows {
+                    wtr.write_byte_record(&row)?;
+                }
+                count += n;
+                if next + window < nchunks {
+                    start_chunk(next + window);
+                }
+                next += 1;
+            }
         }
+        Ok(count)
+    }
+}
+
+/// Searcher decides which rows match and builds the rows that are written.
+struct Searcher {
+    filters: Vec<ColumnFilter>,
+    any: bool,
+    invert: bool,
+    unparseable: Unparseable,
+    count: bool,
+    flag: bool,
+    row_numbers: bool,
+    match_column: bool,
+}
+
+impl Searcher {
+    fn new(
+        args: &Args,
+        headers: &csv::ByteRecord,
+        sel: Selection,
+    ) -> CliResult<Searcher> {
+        let mut filters = vec![];
+        if let Some(ref path) = args.flag_patterns_file {
+            filters.push(ColumnFilter {
+                sel: sel.clone(),
+                filter: pattern_set(path, args)?,
+                negate: false,
+            });
+        }
+        for spec in &args.flag_pattern {
+            filters.push(parse_pattern(spec, headers, args)?);
+        }
+        if filters.is_empty() {
+            let (typ, bound) = (args.flag_type, &*args.arg_regex);
+            let exclusive = args.flag_exclusive;
+            let filter = if args.flag_greater_than {
+                comparison(typ, bound, if exclusive { &GT } else { &GEQ })?
+            } else if args.flag_less_than {
+                comparison(typ, bound, if exclusive { &LT } else { &LEQ })?
+            } else {
+                Filter::Eq(RegexBuilder::new(&*args.arg_regex)
+                    .case_insensitive(args.flag_ignore_case)
+                    .build()?)
+            };
+            filters.push(ColumnFilter {
+                sel: sel,
+                filter: filter,
+                negate: false,
+            });
+        }
+        Ok(Searcher {
+            filters: filters,
+            any: args.flag_any,
+            invert: args.flag_invert_match,
+            unparseable: args.flag_unparseable,
+            count: args.flag_count,
+            flag: args.flag_flag.is_some(),
+            row_numbers: args.flag_row_numbers,
+            match_column: args.flag_match_column.is_some(),
+        })
+    }
+
+    fn is_match(&self, record: &csv::ByteRecord) -> CliResult<bool> {
+        let mut m = !self.any;
+        for f in &self.filters {
+            if f.apply(record, self.unparseable)? == self.any {
+                m = self.any;
+                break;
+            }
+        }
+        Ok(m != self.invert)
+    }
+
+    /// Search `records`, whose first row has the number `first`, passing
+    /// each row to write to `emit`. Returns the number of matching rows.
+    fn search<I, F>(
+        &self,
+        records: I,
+        first: u64,
+        mut emit: F,
+    ) -> CliResult<u64>
+        where I: Iterator<Item=csv::Result<csv::ByteRecord>>,
+              F: FnMut(csv::ByteRecord) -> CliResult<()>
+    {
+        let mut count = 0;
+        for (i, record) in records.enumerate() {
+            let record = record?;
+            let m = self.is_match(&record)?;
+            if m {
+                count += 1;
+            }
+            if self.count || (!m && !self.flag) {
+                continue;
+            }
+            let mut row = csv::ByteRecord::new();
+            if self.row_numbers {
+                row.push_field((first + i as u64).to_string().as_bytes());
+            }
+            row.extend(&record);
+            if self.match_column {
+                let matched = self.filters.iter()
+                    .filter_map(|f| f.set_match(&record))
+                    .next()
+                    .unwrap_or("");
+                row.push_field(matched.as_bytes());
+            }
+            if self.flag {
+                row.push_field(if m { b"true" } else { b"false" });
+            }
+            emit(row)?;
+        }
+        Ok(count)
     }
-    Ok(wtr.flush()?)
 }
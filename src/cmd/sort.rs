@@ -1,8 +1,21 @@
 use std::cmp;
+use std::env;
+use std::fs;
+use std::io;
+use std::iter;
+use std::path::PathBuf;
+use std::process;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use channel;
+use csv;
+use serde::de::{Deserializer, Deserialize, Error};
+use threadpool::ThreadPool;
 
 use CliResult;
 use config::{Config, Delimiter};
-use select::SelectColumns;
+use select::{SelectColumns, Selection};
 use util;
 use std::str::from_utf8;
 
@@ -11,16 +24,64 @@ use self::Number::{Float, Int};
 static USAGE: &'static str = "
 Sorts CSV data lexicographically.
 
-Note that this requires reading all of the CSV data into memory.
+The sort is stable: records that compare equal are written in the same order
+in which they appear in the input. With --unique, only the first of each
+group of equal records is written.
+
+Note that by default this requires reading all of the CSV data into memory.
+When --memory is given, at most roughly that much record data is held in
+memory at once. Larger inputs are sorted in pieces that are written to
+temporary files and then merged, which produces the same output as sorting
+everything in memory.
+
+Each column (or range of columns) given to --select may be followed by
+modifiers that change how it is compared. For example,
+
+    xsv sort -s 'country,revenue:num:desc,name:icase' data.csv
+
+sorts by country, then by revenue as numbers in descending order, then by
+name ignoring case. The available modifiers are:
+
+    asc, desc      Sort in ascending or descending order.
+    lex            Compare the raw bytes of each field.
+    num            Compare fields as numbers, like --numeric.
+    natural        Compare runs of digits as numbers, so that 'a2' sorts
+                   before 'a10'. Leading zeros are ignored, so 'a01'
+                   and 'a1' are equal.
+    icase          Compare fields ignoring case, so 'Alice' and 'alice'
+                   are equal.
+    nulls-first,
+    nulls-last     Put empty fields first or last, regardless of whether
+                   the sort order is ascending or descending.
+
+Columns without modifiers are sorted according to --numeric and --reverse.
+Modifiers cannot be used with an inverted ('!') selection.
 
 Usage:
     xsv sort [options] [<input>]
 
 sort options:
-    -s, --select <arg>     Select a subset of columns to sort.
+    -s, --select <arg>     Select a subset of columns to sort, with optional
+                           modifiers for each column (see above).
                            See 'xsv select --help' for the format details.
     -N, --numeric          Compare according to string numerical value
     -R, --reverse          Reverse order
+    -u, --unique           Only write the first record of each run of records
+                           whose selected columns compare equal.
+    --memory <arg>         Limit the amount of record data kept in memory.
+                           Sorted runs are spilled to temporary files
+                           whenever the limit is exceeded. The size may
+                           have a K, M or G suffix (e.g., '512M').
+    --tmp-dir <arg>        The directory to write temporary files to when
+                           a memory limit is set. Defaults to the system's
+                           temporary directory.
+    -j, --jobs <arg>       The number of jobs to run in parallel when sorting
+                           in memory. The records are split into chunks that
+                           are sorted in parallel and then merged. The output
+                           is the same as with a single job.
+                           When set to '0', the number of jobs is set to the
+                           number of CPUs detected.
+                           [default: 1]
 
 Common options:
     -h, --help             Display this message
@@ -36,9 +97,13 @@ Common options:
 #[derive(Deserialize)]
 struct Args {
     arg_input: Option<String>,
-    flag_select: SelectColumns,
+    flag_select: SortSpec,
     flag_numeric: bool,
     flag_reverse: bool,
+    flag_unique: bool,
+    flag_memory: Option<ByteSize>,
+    flag_tmp_dir: Option<String>,
+    flag_jobs: usize,
     flag_output: Option<String>,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
@@ -46,52 +111,570 @@ struct Args {
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
-    let numeric = args.flag_numeric;
-    let reverse = args.flag_reverse;
     let rconfig = Config::new(&args.arg_input)
         .delimiter(args.flag_delimiter)
-        .no_headers(args.flag_no_headers)
-        .select(args.flag_select);
+        .no_headers(args.flag_no_headers);
 
     let mut rdr = rconfig.reader()?;
 
     let headers = rdr.byte_headers()?.clone();
-    let sel = rconfig.selection(&headers)?;
-
-    let mut all = rdr.byte_records().collect::<Result<Vec<_>, _>>()?;
-    match (numeric, reverse) {
-        (false, false) =>
-            all.sort_by(|r1, r2| {
-                let a = sel.select(r1);
-                let b = sel.select(r2);
-                iter_cmp(a, b)
-            }),
-        (true, false) =>
-            all.sort_by(|r1, r2| {
-                let a = sel.select(r1);
-                let b = sel.select(r2);
-                iter_cmp_num(a, b)
-            }),
-        (false, true) =>
-            all.sort_by(|r1, r2| {
-                let a = sel.select(r1);
-                let b = sel.select(r2);
-                iter_cmp(b, a)
-            }),
-        (true, true) =>
-            all.sort_by(|r1, r2| {
-                let a = sel.select(r1);
-                let b = sel.select(r2);
-                iter_cmp_num(b, a)
-            }),
-    }
+    let default = KeyOptions {
+        kind: Some(if args.flag_numeric { Kind::Numeric } else { Kind::Lex }),
+        reverse: Some(args.flag_reverse),
+        nulls: None,
+    };
+    let cmp = Arc::new(args.flag_select.comparator(
+        &headers, !rconfig.no_headers, &default)?);
 
     let mut wtr = Config::new(&args.flag_output).writer()?;
     rconfig.write_headers(&mut rdr, &mut wtr)?;
-    for r in all.into_iter() {
-        wtr.write_byte_record(&r)?;
+    let mut out = Output::new(&cmp, wtr, args.flag_unique);
+    match args.flag_memory {
+        None => {
+            let mut all = rdr.byte_records().collect::<Result<Vec<_>, _>>()?;
+            if args.njobs() > 1 {
+                let runs = parallel_sort(
+                    &cmp, all, args.njobs(), args.flag_unique);
+                merge_runs(&cmp, runs, &mut out)?;
+            } else {
+                sort_run(&cmp, &mut all, args.flag_unique);
+                for r in all.into_iter() {
+                    out.write(r)?;
+                }
+            }
+        }
+        Some(ByteSize(limit)) => {
+            let tmp_dir = match args.flag_tmp_dir {
+                None => env::temp_dir(),
+                Some(ref dir) => PathBuf::from(dir),
+            };
+            external_sort(&cmp, limit, tmp_dir, &mut rdr, &mut out)?;
+        }
+    }
+    out.flush()
+}
+
+impl Args {
+    fn njobs(&self) -> usize {
+        if self.flag_jobs == 0 { util::num_cpus() } else { self.flag_jobs }
+    }
+}
+
+/// Output writes sorted records.
+///
+/// When `unique` is set, a record is dropped if it compares equal to the
+/// record written before it.
+struct Output<'a, W: io::Write> {
+    cmp: &'a Comparator,
+    wtr: csv::Writer<W>,
+    unique: bool,
+    last: Option<csv::ByteRecord>,
+}
+
+impl<'a, W: io::Write> Output<'a, W> {
+    fn new(
+        cmp: &'a Comparator,
+        wtr: csv::Writer<W>,
+        unique: bool,
+    ) -> Output<'a, W> {
+        Output { cmp: cmp, wtr: wtr, unique: unique, last: None }
+    }
+
+    fn write(&mut self, record: csv::ByteRecord) -> CliResult<()> {
+        if !self.unique {
+            return Ok(self.wtr.write_byte_record(&record)?);
+        }
+        if let Some(ref last) = self.last {
+            if self.cmp.cmp(last, &record) == cmp::Ordering::Equal {
+                return Ok(());
+            }
+        }
+        self.wtr.write_byte_record(&record)?;
+        self.last = Some(record);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> CliResult<()> {
+        Ok(self.wtr.flush()?)
+    }
+}
+
+/// Comparator orders records by a sequence of sort keys.
+///
+/// Records are compared by the first key, with ties broken by each
+/// subsequent key.
+struct Comparator {
+    keys: Vec<SortKey>,
+}
+
+impl Comparator {
+    fn cmp(
+        &self,
+        r1: &csv::ByteRecord,
+        r2: &csv::ByteRecord,
+    ) -> cmp::Ordering {
+        for key in &self.keys {
+            match key.cmp(r1, r2) {
+                cmp::Ordering::Equal => (),
+                non_eq => return non_eq,
+            }
+        }
+        cmp::Ordering::Equal
+    }
+}
+
+/// SortKey compares records by one item of a sort specification, which may
+/// span several columns (e.g., a range like `1-3`).
+struct SortKey {
+    sel: Selection,
+    kind: Kind,
+    reverse: bool,
+    nulls: Option<Nulls>,
+}
+
+impl SortKey {
+    fn cmp(
+        &self,
+        r1: &csv::ByteRecord,
+        r2: &csv::ByteRecord,
+    ) -> cmp::Ordering {
+        let nulls = match self.nulls {
+            None => {
+                return self.cmp_fields(self.sel.select(r1),
+                                       self.sel.select(r2));
+            }
+            Some(nulls) => nulls,
+        };
+        // Null placement doesn't depend on the sort direction, so we need to
+        // look at each field individually.
+        for &i in self.sel.iter() {
+            let (a, b) = (&r1[i], &r2[i]);
+            let ord = match (a.is_empty(), b.is_empty()) {
+                (true, true) => cmp::Ordering::Equal,
+                (true, false) => nulls.null_ordering(),
+                (false, true) => nulls.null_ordering().reverse(),
+                (false, false) => {
+                    self.cmp_fields(iter::once(a), iter::once(b))
+                }
+            };
+            if ord != cmp::Ordering::Equal {
+                return ord;
+            }
+        }
+        cmp::Ordering::Equal
+    }
+
+    fn cmp_fields<'a, L, R>(&self, a: L, b: R) -> cmp::Ordering
+            where L: Iterator<Item=&'a [u8]>, R: Iterator<Item=&'a [u8]> {
+        let ord = match self.kind {
+            Kind::Lex => iter_cmp(a, b),
+            Kind::Numeric => iter_cmp_num(a, b),
+            Kind::Natural => iter_cmp_by(a, b, cmp_natural),
+            Kind::FoldCase => iter_cmp_by(a, b, cmp_fold_case),
+        };
+        if self.reverse { ord.reverse() } else { ord }
+    }
+}
+
+/// The way in which the fields of a sort key are compared.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Kind {
+    Lex,
+    Numeric,
+    Natural,
+    FoldCase,
+}
+
+/// Where empty fields are placed, independent of sort direction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Nulls {
+    First,
+    Last,
+}
+
+impl Nulls {
+    /// The ordering of a null field relative to a non-null field.
+    fn null_ordering(self) -> cmp::Ordering {
+        match self {
+            Nulls::First => cmp::Ordering::Less,
+            Nulls::Last => cmp::Ordering::Greater,
+        }
+    }
+}
+
+/// The modifiers given to one item of a sort specification. Any modifier
+/// that isn't given falls back to the default set by the global flags.
+#[derive(Clone, Debug, Default)]
+struct KeyOptions {
+    kind: Option<Kind>,
+    reverse: Option<bool>,
+    nulls: Option<Nulls>,
+}
+
+impl KeyOptions {
+    /// Apply the modifier `name`, returning false if it isn't a known
+    /// modifier.
+    fn set(&mut self, name: &str) -> bool {
+        match name {
+            "asc" => self.reverse = Some(false),
+            "desc" => self.reverse = Some(true),
+            "lex" => self.kind = Some(Kind::Lex),
+            "num" => self.kind = Some(Kind::Numeric),
+            "natural" => self.kind = Some(Kind::Natural),
+            "icase" => self.kind = Some(Kind::FoldCase),
+            "nulls-first" => self.nulls = Some(Nulls::First),
+            "nulls-last" => self.nulls = Some(Nulls::Last),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// SortSpec is the parsed form of the `--select` argument: a list of column
+/// selections, each with its own comparison modifiers.
+#[derive(Clone, Debug)]
+struct SortSpec {
+    keys: Vec<(SelectColumns, KeyOptions)>,
+}
+
+impl SortSpec {
+    fn parse(raw: &str) -> Result<SortSpec, String> {
+        if raw.starts_with('!') {
+            return Ok(SortSpec {
+                keys: vec![(SelectColumns::parse(raw)?, KeyOptions::default())],
+            });
+        }
+        let mut keys = vec![];
+        for item in util::split_unquoted(raw, ',') {
+            let mut item = item;
+            let mut opts = KeyOptions::default();
+            // Modifiers are peeled off from the right, so the last one given
+            // wins when two of them conflict. Reverse them first so that it
+            // instead reads left to right.
+            let mut mods = vec![];
+            while let Some(i) = item.rfind(':') {
+                if !is_modifier(&item[i+1..]) {
+                    break;
+                }
+                mods.push(&item[i+1..]);
+                item = &item[..i];
+            }
+            for name in mods.into_iter().rev() {
+                opts.set(name);
+            }
+            if item.is_empty() {
+                return Err(format!(
+                    "Sort key '{}' is missing a column selection.", raw));
+            }
+            keys.push((SelectColumns::parse(item)?, opts));
+        }
+        if keys.is_empty() {
+            keys.push((SelectColumns::parse("")?, KeyOptions::default()));
+        }
+        Ok(SortSpec { keys: keys })
+    }
+
+    fn comparator(
+        &self,
+        headers: &csv::ByteRecord,
+        use_names: bool,
+        default: &KeyOptions,
+    ) -> Result<Comparator, String> {
+        let mut keys = vec![];
+        for &(ref sel, ref opts) in &self.keys {
+            keys.push(SortKey {
+                sel: sel.selection(headers, use_names)?,
+                kind: opts.kind.or(default.kind).unwrap_or(Kind::Lex),
+                reverse: opts.reverse.or(default.reverse).unwrap_or(false),
+                nulls: opts.nulls.or(default.nulls),
+            });
+        }
+        Ok(Comparator { keys: keys })
+    }
+}
+
+impl<'de> Deserialize<'de> for SortSpec {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<SortSpec, D::Error> {
+        let raw = String::deserialize(d)?;
+        SortSpec::parse(&raw).map_err(|e| D::Error::custom(&e))
+    }
+}
+
+fn is_modifier(name: &str) -> bool {
+    KeyOptions::default().set(name)
+}
+
+type Records = Box<Iterator<Item=csv::Result<csv::ByteRecord>>>;
+
+/// The largest number of runs that are merged at once. This bounds the
+/// number of temporary files that are open at the same time.
+const MERGE_FAN_IN: usize = 64;
+
+/// Sort the records in `rdr` while holding roughly at most `limit` bytes of
+/// record data in memory.
+///
+/// Whenever the buffered records exceed `limit`, they are sorted and written
+/// to a temporary file in `tmp_dir` as a "run." If there are too many runs to
+/// merge at once, they are first merged into fewer, larger runs. The runs
+/// (along with whatever is left in memory) are then merged into `out`.
+fn external_sort<R: io::Read, W: io::Write>(
+    cmp: &Comparator,
+    limit: usize,
+    tmp_dir: PathBuf,
+    rdr: &mut csv::Reader<R>,
+    out: &mut Output<W>,
+) -> CliResult<()> {
+    let mut runs = TempRuns::new(tmp_dir);
+    let mut buf = vec![];
+    let mut buf_size = 0;
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        buf_size += record_size(&record);
+        buf.push(record.clone());
+        if buf_size > limit {
+            sort_run(cmp, &mut buf, out.unique);
+            runs.write(&buf)?;
+            buf.clear();
+            buf_size = 0;
+        }
+    }
+    sort_run(cmp, &mut buf, out.unique);
+    runs.reduce(cmp, out.unique, MERGE_FAN_IN)?;
+
+    let mut sources = runs.readers()?;
+    sources.push(Box::new(buf.into_iter().map(Ok)));
+    merge_runs(cmp, sources, out)
+}
+
+/// Sort a single run of records in place.
+///
+/// When `unique` is set, duplicates are removed from the run early so that
+/// less data needs to be written to disk. (The merge still removes
+/// duplicates that span runs.)
+fn sort_run(cmp: &Comparator, run: &mut Vec<csv::ByteRecord>, unique: bool) {
+    run.sort_by(|r1, r2| cmp.cmp(r1, r2));
+    if unique {
+        run.dedup_by(|r2, r1| cmp.cmp(r1, r2) == cmp::Ordering::Equal);
+    }
+}
+
+/// Sort `records` by splitting them into chunks that are sorted in parallel
+/// on `njobs` threads.
+///
+/// The sorted chunks are returned in input order, so that merging them with
+/// `merge_runs` is stable.
+fn parallel_sort(
+    cmp: &Arc<Comparator>,
+    mut records: Vec<csv::ByteRecord>,
+    njobs: usize,
+    unique: bool,
+) -> Vec<Records> {
+    let chunk_size = util::chunk_size(records.len(), njobs);
+    let nchunks = util::num_of_chunks(records.len(), chunk_size);
+
+    // Split chunks off of the end so that records aren't copied.
+    let mut chunks = vec![];
+    for i in (0..nchunks).rev() {
+        chunks.push(records.split_off(i * chunk_size));
+    }
+    chunks.reverse();
+
+    let pool = ThreadPool::new(njobs);
+    let mut results = vec![];
+    for mut chunk in chunks.into_iter() {
+        let (send, recv) = channel::bounded(0);
+        results.push(recv);
+        let cmp = cmp.clone();
+        pool.execute(move || {
+            sort_run(&cmp, &mut chunk, unique);
+            send.send(chunk);
+        });
+    }
+    results.into_iter().map(|recv| -> Records {
+        Box::new(recv.recv().unwrap().into_iter().map(Ok))
+    }).collect()
+}
+
+/// Merge already sorted runs of records into `out`.
+///
+/// When records from different runs compare equal, the record from the
+/// earlier run is written first. This makes the merge stable as long as the
+/// runs are given in input order.
+fn merge_runs<W: io::Write>(
+    cmp: &Comparator,
+    mut runs: Vec<Records>,
+    out: &mut Output<W>,
+) -> CliResult<()> {
+    let mut heads: Vec<Option<csv::ByteRecord>> = vec![];
+    for run in runs.iter_mut() {
+        heads.push(match run.next() {
+            None => None,
+            Some(r) => Some(r?),
+        });
+    }
+    // `order` contains the index of every run that still has records,
+    // sorted by the run's current head record.
+    let mut order: Vec<usize> =
+        (0..heads.len()).filter(|&i| heads[i].is_some()).collect();
+    order.sort_by(|&i, &j| {
+        cmp.cmp(heads[i].as_ref().unwrap(), heads[j].as_ref().unwrap())
+           .then(i.cmp(&j))
+    });
+    while !order.is_empty() {
+        let i = order.remove(0);
+        out.write(heads[i].take().unwrap())?;
+        heads[i] = match runs[i].next() {
+            None => continue,
+            Some(r) => Some(r?),
+        };
+        let pos = {
+            let head = heads[i].as_ref().unwrap();
+            order.binary_search_by(|&j| {
+                cmp.cmp(heads[j].as_ref().unwrap(), head).then(j.cmp(&i))
+            }).unwrap_err()
+        };
+        order.insert(pos, i);
+    }
+    Ok(())
+}
+
+/// An estimate of the number of bytes of memory used by `record`.
+fn record_size(record: &csv::ByteRecord) -> usize {
+    record.as_slice().len()
+    + (record.len() * ::std::mem::size_of::<usize>())
+    + ::std::mem::size_of::<csv::ByteRecord>()
+}
+
+/// TempRuns tracks the temporary files holding sorted runs.
+///
+/// The files are removed when this value is dropped.
+struct TempRuns {
+    dir: PathBuf,
+    /// The runs, in input order.
+    paths: Vec<PathBuf>,
+    /// The number of files created so far, used to name new files.
+    created: usize,
+}
+
+impl TempRuns {
+    fn new(dir: PathBuf) -> TempRuns {
+        TempRuns { dir: dir, paths: vec![], created: 0 }
+    }
+
+    /// Create a new temporary file. The caller is responsible for adding its
+    /// path to `paths`, so that it is removed.
+    fn create(&mut self) -> CliResult<(PathBuf, csv::Writer<fs::File>)> {
+        let path = self.dir.join(format!(
+            "xsv-sort-{}-{}.csv", process::id(), self.created));
+        self.created += 1;
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(|err| format!(
+                "failed to create temporary file {}: {}",
+                path.display(), err))?;
+        let wtr = csv::WriterBuilder::new()
+            .flexible(true)
+            .from_writer(file);
+        Ok((path, wtr))
+    }
+
+    /// Write `records` to a new run.
+    fn write(&mut self, records: &[csv::ByteRecord]) -> CliResult<()> {
+        let (path, mut wtr) = self.create()?;
+        self.paths.push(path);
+        for r in records {
+            wtr.write_byte_record(r)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+
+    /// Merge runs, `fan_in` at a time, until there are at most `fan_in`.
+    ///
+    /// Each group of neighbouring runs is replaced by a single run in the
+    /// same position, so the runs stay in input order and the final merge
+    /// is still stable.
+    fn reduce(
+        &mut self,
+        cmp: &Comparator,
+        unique: bool,
+        fan_in: usize,
+    ) -> CliResult<()> {
+        while self.paths.len() > fan_in {
+            let mut next = 0;
+            while next < self.paths.len() {
+                let end = cmp::min(next + fan_in, self.paths.len());
+                if end - next > 1 {
+                    let (path, wtr) = self.create()?;
+                    self.paths.insert(next, path);
+                    let runs = open_runs(&self.paths[next + 1..end + 1])?;
+                    let mut out = Output::new(cmp, wtr, unique);
+                    merge_runs(cmp, runs, &mut out)?;
+                    out.flush()?;
+                    for path in self.paths.drain(next + 1..end + 1) {
+                        let _ = fs::remove_file(path);
+                    }
+                }
+                next += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Open a reader for each run, in the order they were written.
+    fn readers(&self) -> CliResult<Vec<Records>> {
+        open_runs(&self.paths)
+    }
+}
+
+/// Open a reader for each of the runs in `paths`.
+fn open_runs(paths: &[PathBuf]) -> CliResult<Vec<Records>> {
+    let mut rdrs: Vec<Records> = vec![];
+    for path in paths {
+        let rdr = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(path)?;
+        rdrs.push(Box::new(rdr.into_byte_records()));
+    }
+    Ok(rdrs)
+}
+
+impl Drop for TempRuns {
+    fn drop(&mut self) {
+        for path in &self.paths {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// A number of bytes given on the command line, optionally with a 'K', 'M'
+/// or 'G' suffix.
+#[derive(Clone, Copy, Debug)]
+struct ByteSize(usize);
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<ByteSize, D::Error> {
+        let raw = String::deserialize(d)?;
+        let (num, scale) = match raw.chars().last() {
+            Some('K') | Some('k') => (&raw[..raw.len()-1], 1 << 10),
+            Some('M') | Some('m') => (&raw[..raw.len()-1], 1 << 20),
+            Some('G') | Some('g') => (&raw[..raw.len()-1], 1 << 30),
+            _ => (&raw[..], 1),
+        };
+        let n = match usize::from_str(num) {
+            Ok(n) if n > 0 => n,
+            _ => return Err(D::Error::custom(format!(
+                "Could not convert '{}' to a positive size in bytes.", raw))),
+        };
+        match n.checked_mul(scale) {
+            Some(size) => Ok(ByteSize(size)),
+            None => Err(D::Error::custom(format!(
+                "The size '{}' is too large.", raw))),
+        }
     }
-    Ok(wtr.flush()?)
 }
 
 /// Order `a` and `b` lexicographically using `Ord`
@@ -110,6 +693,23 @@ pub fn iter_cmp<A, L, R>(mut a: L, mut b: R) -> cmp::Ordering
     }
 }
 
+/// Order `a` and `b` lexicographically using `cmp` to compare elements.
+fn iter_cmp_by<A, L, R, F>(mut a: L, mut b: R, cmp: F) -> cmp::Ordering
+        where L: Iterator<Item=A>, R: Iterator<Item=A>,
+              F: Fn(A, A) -> cmp::Ordering {
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return cmp::Ordering::Equal,
+            (None, _   ) => return cmp::Ordering::Less,
+            (_   , None) => return cmp::Ordering::Greater,
+            (Some(x), Some(y)) => match cmp(x, y) {
+                cmp::Ordering::Equal => (),
+                non_eq => return non_eq,
+            },
+        }
+    }
+}
+
 /// Try parsing `a` and `b` as numbers when ordering
 pub fn iter_cmp_num<'a, L, R>(mut a: L, mut b: R) -> cmp::Ordering
         where L: Iterator<Item=&'a [u8]>, R: Iterator<Item=&'a [u8]> {
@@ -147,6 +747,47 @@ fn compare_float(f1: f64, f2: f64) -> cmp::Ordering {
 
 
 
+/// Compare `a` and `b` case insensitively.
+fn cmp_fold_case(a: &[u8], b: &[u8]) -> cmp::Ordering {
+    let (sa, sb) = (String::from_utf8_lossy(a), String::from_utf8_lossy(b));
+    let fa = sa.chars().flat_map(|c| c.to_lowercase());
+    let fb = sb.chars().flat_map(|c| c.to_lowercase());
+    iter_cmp(fa, fb)
+}
+
+/// Compare `a` and `b` such that runs of ASCII digits are ordered by their
+/// numeric value, e.g., `file2` comes before `file10`. Leading zeros are
+/// ignored, so `a01` and `a1` are equal.
+fn cmp_natural(a: &[u8], b: &[u8]) -> cmp::Ordering {
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let ord = if a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            let (si, sj) = (i, j);
+            while i < a.len() && a[i].is_ascii_digit() { i += 1; }
+            while j < b.len() && b[j].is_ascii_digit() { j += 1; }
+            cmp_digits(&a[si..i], &b[sj..j])
+        } else {
+            i += 1;
+            j += 1;
+            a[i-1].cmp(&b[j-1])
+        };
+        if ord != cmp::Ordering::Equal {
+            return ord;
+        }
+    }
+    (a.len() - i).cmp(&(b.len() - j))
+}
+
+/// Compare two runs of ASCII digits by their numeric value.
+fn cmp_digits(a: &[u8], b: &[u8]) -> cmp::Ordering {
+    fn trim_zeros(mut s: &[u8]) -> &[u8] {
+        while s.len() > 1 && s[0] == b'0' { s = &s[1..]; }
+        s
+    }
+    let (a, b) = (trim_zeros(a), trim_zeros(b));
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
 fn next_num<'a, X>(xs: &mut X) -> Option<Number>
         where X: Iterator<Item=&'a [u8]> {
     xs.next()
@@ -1,23 +1,33 @@
 use std::borrow::ToOwned;
+use std::cmp::Ordering;
+use std::collections::hash_map::{Entry, HashMap};
+use std::cmp;
 use std::default::Default;
 use std::fmt;
 use std::fs;
-use std::io;
+use std::io::{self, Write};
 use std::iter::{FromIterator, repeat};
 use std::str::{self, FromStr};
 
 use channel;
 use csv;
 use stats::{Commute, OnlineStats, MinMax, Unsorted, merge_all};
+use serde::de::{Deserializer, Deserialize, Error};
 use threadpool::ThreadPool;
 
 use CliResult;
 use config::{Config, Delimiter};
+use date::{DateFormat, DateTime};
+use hyperloglog::{self, HyperLogLog};
 use index::Indexed;
+use json;
 use select::{SelectColumns, Selection};
+use tdigest::TDigest;
 use util;
 
-use self::FieldType::{TUnknown, TNull, TUnicode, TFloat, TInteger};
+use self::FieldType::{
+    TUnknown, TNull, TUnicode, TFloat, TInteger, TDate, TDateTime, TBoolean,
+};
 
 static USAGE: &'static str = "
 Computes basic statistics on CSV data.
@@ -29,9 +39,36 @@ be enabled explicitly. By default, the following statistics are reported for
 default set of statistics corresponds to statistics that can be computed
 efficiently on a stream of data (i.e., constant memory).
 
+The default statistics also include the min, max and mean length of the
+values (in bytes), and the number of empty values (NULLs), their percentage
+of all values and the number of values that aren't empty.
+
+The median, quartiles and percentiles are interpolated linearly between the
+two nearest values. With --approx they are estimated instead, which is
+usually accurate to a fraction of a percent, and most accurate for extreme
+percentiles like the 99th. The cardinality is also estimated with --approx,
+with a relative standard error given by --error-rate.
+
+The type of each column is inferred from its values. Besides numbers, the
+types include booleans ('true' or 'false', ignoring case), dates and
+date-times. Dates and date-times are recognized in ISO 8601 format (e.g.,
+'2018-03-01' or '2018-03-01T12:30:00Z'), and in any other formats given
+with --date-formats. Their min and max are chronological, and their range is
+an ISO 8601 duration such as 'P3DT4H'.
+
+The range (max - min), mean length, null count, null percentage and
+non-empty count come after the median, mode and cardinality in the output,
+followed by the quartiles and percentiles when they're enabled. This keeps
+the other columns in the positions they have always had.
+
 Computing statistics on a large file can be made much faster if you create
 an index for it first with 'xsv index'.
 
+With --group-by, the rows are split into groups by the values of the given
+columns, and statistics are computed separately for each group. The output
+then starts with the group columns, and has one row for each field in each
+group. Groups are written in the order that they first appear.
+
 Usage:
     xsv stats [options] [<input>]
 
@@ -44,11 +81,32 @@ stats options:
     --mode                 Show the mode.
                            This requires storing all CSV data in memory.
     --cardinality          Show the cardinality.
-                           This requires storing all CSV data in memory.
+                           This requires storing all CSV data in memory,
+                           unless --approx is given.
     --median               Show the median.
                            This requires storing all CSV data in memory.
+    --quartiles            Show the first and third quartiles and the
+                           interquartile range (IQR).
+                           This requires storing all CSV data in memory.
+    --percentiles <list>   Show the given percentiles, as a comma separated
+                           list of numbers between 0 and 100, e.g., '90,99'.
+                           This requires storing all CSV data in memory.
+    --approx               Estimate the median, quartiles and percentiles
+                           using a t-digest, and the cardinality using a
+                           HyperLogLog sketch. These only require a small,
+                           constant amount of memory for each column.
+    --error-rate <arg>     The relative standard error of the approximate
+                           cardinality. Smaller values use more memory.
+                           It must be at least 0.00204.
+                           [default: 0.01]
+    --date-formats <list>  Additional formats for dates and date-times, as a
+                           semicolon separated list, e.g., '%d/%m/%Y;%Y%m%d
+                           %H:%M'. See below for the format specifiers.
     --nulls                Include NULLs in the population size for computing
                            mean and standard deviation.
+    -g, --group-by <arg>   Compute statistics for each group of rows with the
+                           same values in these columns. The columns are
+                           selected in the same way as for --select.
     -j, --jobs <arg>       The number of jobs to run in parallel.
                            This works better when the given CSV data has
                            an index already created. Note that a file handle
@@ -60,11 +118,26 @@ stats options:
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
+    --json                 Write the statistics as a JSON array, with one
+                           object for each field. Statistics are JSON
+                           numbers where possible, and empty statistics
+                           are null. With --group-by, each object has a
+                           'group' object with the values of the group
+                           columns.
     -n, --no-headers       When set, the first row will NOT be interpreted
                            as column names. i.e., They will be included
                            in statistics.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
                            Must be a single character. (default: ,)
+
+Date formats are written with these specifiers, and everything else in a
+format must match exactly:
+
+    %Y                     A four digit year.
+    %m, %d                 A month or day, with one or two digits.
+    %b                     An abbreviated month name, e.g., 'Jan'.
+    %H, %M, %S             An hour (0 to 23), minute or second.
+    %%                     A literal '%'.
 ";
 
 #[derive(Clone, Deserialize)]
@@ -75,18 +148,29 @@ struct Args {
     flag_mode: bool,
     flag_cardinality: bool,
     flag_median: bool,
+    flag_quartiles: bool,
+    flag_percentiles: Option<Percentiles>,
+    flag_approx: bool,
+    flag_error_rate: f64,
+    flag_date_formats: Option<DateFormats>,
     flag_nulls: bool,
+    flag_group_by: Option<SelectColumns>,
     flag_jobs: usize,
     flag_output: Option<String>,
+    flag_json: bool,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
+    if !(args.flag_error_rate >= hyperloglog::MIN_ERROR
+            && args.flag_error_rate < 1.0) {
+        return fail!(format!("--error-rate must be at least {} and less \
+                              than 1.", hyperloglog::MIN_ERROR));
+    }
 
-    let mut wtr = Config::new(&args.flag_output).writer()?;
-    let (headers, stats) = match args.rconfig().indexed()? {
+    let (headers, group_headers, groups) = match args.rconfig().indexed()? {
         None => args.sequential_stats(),
         Some(idx) => {
             if args.flag_jobs == 1 {
@@ -96,36 +180,115 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
             }
         }
     }?;
-    let stats = args.stats_to_records(stats);
-
-    wtr.write_record(&args.stat_headers())?;
-    let fields = headers.iter().zip(stats.into_iter());
-    for (i, (header, stat)) in fields.enumerate() {
-        let header =
-            if args.flag_no_headers {
-                i.to_string().into_bytes()
-            } else {
-                header.to_vec()
-            };
+
+    let stat_names = args.stat_names();
+    // Convert the stats of every group at once, so that they share one
+    // thread pool.
+    let mut keys = vec![];
+    let mut all_stats = vec![];
+    for (key, stats) in groups.into_sorted() {
+        keys.push(key);
+        all_stats.extend(stats);
+    }
+    let mut records = args.stats_to_records(all_stats).into_iter();
+    let mut rows = vec![];
+    for key in keys {
+        for (i, header) in headers.iter().enumerate() {
+            let header =
+                if args.flag_no_headers {
+                    i.to_string().into_bytes()
+                } else {
+                    header.to_vec()
+                };
+            rows.push((key.clone(), header, records.next().unwrap()));
+        }
+    }
+
+    if args.flag_json {
+        let objects: Vec<String> = rows.iter().map(|row| {
+            stat_to_json(&group_headers, &stat_names, &row.0, &row.1, &row.2)
+        }).collect();
+        let mut wtr = Config::new(&args.flag_output).io_writer()?;
+        writeln!(wtr, "{}", json::array(&objects))?;
+        wtr.flush()?;
+        return Ok(());
+    }
+
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let names = group_headers.iter()
+        .chain(stat_names.iter().map(|n| n.as_bytes()));
+    wtr.write_record(names)?;
+    for (key, header, stat) in rows {
         let stat = stat.iter().map(|f| f.as_bytes());
-        wtr.write_record(vec![&*header].into_iter().chain(stat))?;
+        let row = key.iter().map(|f| &**f)
+            .chain(vec![&*header])
+            .chain(stat);
+        wtr.write_record(row)?;
     }
     wtr.flush()?;
     Ok(())
 }
 
+/// Convert one row of statistics to a JSON object.
+///
+/// The statistics are numbers, except for the type, the mode and the min,
+/// max and range of values that aren't numbers, which are strings. Empty
+/// statistics are `null`.
+fn stat_to_json(
+    group_headers: &csv::ByteRecord,
+    stat_names: &[String],
+    key: &[Vec<u8>],
+    field: &[u8],
+    stat: &csv::StringRecord,
+) -> String {
+    let lossy = |s: &[u8]| String::from_utf8_lossy(s).into_owned();
+    let mut obj = json::Object::new();
+    if !group_headers.is_empty() {
+        let mut group = json::Object::new();
+        for (name, value) in group_headers.iter().zip(key) {
+            group.string(&lossy(name), &lossy(value));
+        }
+        obj.raw("group", &group.finish());
+    }
+    obj.string("field", &lossy(field));
+
+    let is_number = stat.get(0) == Some("Integer")
+                    || stat.get(0) == Some("Float");
+    // The first name is "field", which isn't part of `stat`.
+    for (name, value) in stat_names[1..].iter().zip(stat.iter()) {
+        let is_text = match &**name {
+            "type" | "mode" => true,
+            "min" | "max" | "range" => !is_number,
+            _ => false,
+        };
+        if value.is_empty() {
+            obj.null(name);
+        } else if is_text {
+            obj.string(name, value);
+        } else {
+            obj.number(name, value);
+        }
+    }
+    obj.finish()
+}
+
 impl Args {
-    fn sequential_stats(&self) -> CliResult<(csv::ByteRecord, Vec<Stats>)> {
+    /// Compute the stats of the whole input. Returns the names of the
+    /// selected columns and of the --group-by columns along with them.
+    fn sequential_stats(
+        &self,
+    ) -> CliResult<(csv::ByteRecord, csv::ByteRecord, Groups)> {
         let mut rdr = self.rconfig().reader()?;
-        let (headers, sel) = self.sel_headers(&mut rdr)?;
-        let stats = self.compute(&sel, rdr.byte_records())?;
-        Ok((headers, stats))
+        let (headers, sel, group, group_headers) =
+            self.sel_headers(&mut rdr)?;
+        let groups = self.compute(&sel, &group, 0, rdr.byte_records())?;
+        Ok((headers, group_headers, groups))
     }
 
     fn parallel_stats(
         &self,
         idx: Indexed<fs::File, fs::File>,
-    ) -> CliResult<(csv::ByteRecord, Vec<Stats>)> {
+    ) -> CliResult<(csv::ByteRecord, csv::ByteRecord, Groups)> {
         // N.B. This method doesn't handle the case when the number of records
         // is zero correctly. So we use `sequential_stats` instead.
         if idx.count() == 0 {
@@ -133,7 +296,8 @@ impl Args {
         }
 
         let mut rdr = self.rconfig().reader()?;
-        let (headers, sel) = self.sel_headers(&mut rdr)?;
+        let (headers, sel, group, group_headers) =
+            self.sel_headers(&mut rdr)?;
 
         let chunk_size = util::chunk_size(idx.count() as usize, self.njobs());
         let nchunks = util::num_of_chunks(idx.count() as usize, chunk_size);
@@ -142,15 +306,18 @@ impl Args {
         let (send, recv) = channel::bounded(0);
         for i in 0..nchunks {
             let (send, args, sel) = (send.clone(), self.clone(), sel.clone());
+            let group = group.clone();
             pool.execute(move || {
+                let start = (i * chunk_size) as u64;
                 let mut idx = args.rconfig().indexed().unwrap().unwrap();
-                idx.seek((i * chunk_size) as u64).unwrap();
+                idx.seek(start).unwrap();
                 let it = idx.byte_records().take(chunk_size);
-                send.send(args.compute(&sel, it).unwrap());
+                send.send(args.compute(&sel, &group, start, it).unwrap());
             });
         }
         drop(send);
-        Ok((headers, merge_all(recv).unwrap_or_else(Vec::new)))
+        let groups = merge_all(recv).unwrap_or_else(Default::default);
+        Ok((headers, group_headers, groups))
     }
 
     fn stats_to_records(&self, stats: Vec<Stats>) -> Vec<csv::StringRecord> {
@@ -170,25 +337,69 @@ impl Args {
         records
     }
 
-    fn compute<I>(&self, sel: &Selection, it: I) -> CliResult<Vec<Stats>>
+    /// Compute stats for each group of the records in `it`, where `start`
+    /// is the index of the first of them in the whole input.
+    ///
+    /// Without --group-by, every record is in the same group, whose key is
+    /// empty.
+    fn compute<I>(
+        &self,
+        sel: &Selection,
+        group: &Option<Selection>,
+        start: u64,
+        it: I,
+    ) -> CliResult<Groups>
             where I: Iterator<Item=csv::Result<csv::ByteRecord>> {
-        let mut stats = self.new_stats(sel.len());
-        for row in it {
+        let mut groups = Groups::default();
+        if group.is_none() {
+            groups.0.insert(vec![], (start, self.new_stats(sel.len())));
+        }
+        for (i, row) in it.enumerate() {
             let row = row?;
+            let key = match *group {
+                None => vec![],
+                Some(ref group) => {
+                    group.select(&row).map(|f| f.to_vec()).collect()
+                }
+            };
+            let group = groups.0.entry(key).or_insert_with(|| {
+                (start + i as u64, self.new_stats(sel.len()))
+            });
             for (i, field) in sel.select(&row).enumerate() {
-                stats[i].add(field);
+                group.1[i].add(field);
             }
         }
-        Ok(stats)
+        Ok(groups)
     }
 
+    /// Read the headers and resolve the selected columns and the
+    /// --group-by columns, if any. The names of the group columns are
+    /// 1-based indices when there are no headers.
     fn sel_headers<R: io::Read>(
         &self,
         rdr: &mut csv::Reader<R>,
-    ) -> CliResult<(csv::ByteRecord, Selection)> {
+    ) -> CliResult<(csv::ByteRecord, Selection, Option<Selection>,
+                    csv::ByteRecord)> {
         let headers = rdr.byte_headers()?.clone();
         let sel = self.rconfig().selection(&headers)?;
-        Ok((csv::ByteRecord::from_iter(sel.select(&headers)), sel))
+        let mut group_headers = csv::ByteRecord::new();
+        let group = match self.flag_group_by {
+            None => None,
+            Some(ref group) => {
+                let group = group.selection(&headers, !self.flag_no_headers)?;
+                for &i in group.iter() {
+                    if self.flag_no_headers {
+                        group_headers.push_field(
+                            (i + 1).to_string().as_bytes());
+                    } else {
+                        group_headers.push_field(&headers[i]);
+                    }
+                }
+                Some(group)
+            }
+        };
+        let sel_headers = csv::ByteRecord::from_iter(sel.select(&headers));
+        Ok((sel_headers, sel, group, group_headers))
     }
 
     fn rconfig(&self) -> Config {
@@ -210,24 +421,119 @@ impl Args {
             dist: true,
             cardinality: self.flag_cardinality || self.flag_everything,
             median: self.flag_median || self.flag_everything,
+            quartiles: self.flag_quartiles || self.flag_everything,
+            percentiles: self.flag_percentiles.as_ref()
+                             .map_or(vec![], |p| p.0.clone()),
+            approx: self.flag_approx,
+            error_rate: self.flag_error_rate,
+            date_formats: self.flag_date_formats.as_ref()
+                              .map_or(vec![], |f| f.0.clone()),
             mode: self.flag_mode || self.flag_everything,
         })).take(record_len).collect()
     }
 
-    fn stat_headers(&self) -> csv::StringRecord {
-        let mut fields = vec![
-            "field", "type", "sum", "min", "max", "min_length", "max_length",
-            "mean", "stddev",
-        ];
+    fn stat_names(&self) -> Vec<String> {
+        let mut fields: Vec<String> = [
+            "field", "type", "sum", "min", "max", "min_length",
+            "max_length", "mean", "stddev",
+        ].iter().map(|&s| s.to_owned()).collect();
         let all = self.flag_everything;
-        if self.flag_median || all { fields.push("median"); }
-        if self.flag_mode || all { fields.push("mode"); }
-        if self.flag_cardinality || all { fields.push("cardinality"); }
-        csv::StringRecord::from(fields)
+        if self.flag_median || all { fields.push("median".to_owned()); }
+        if self.flag_mode || all { fields.push("mode".to_owned()); }
+        if self.flag_cardinality || all {
+            fields.push("cardinality".to_owned());
+        }
+        // Statistics that were added later go last, so that the others
+        // keep their positions.
+        for &name in &["range", "mean_length", "null_count", "null_percent",
+                       "non_empty_count"] {
+            fields.push(name.to_owned());
+        }
+        if self.flag_quartiles || all {
+            fields.push("q1".to_owned());
+            fields.push("q3".to_owned());
+            fields.push("iqr".to_owned());
+        }
+        if let Some(ref percentiles) = self.flag_percentiles {
+            for p in &percentiles.0 {
+                fields.push(format!("p{}", p));
+            }
+        }
+        fields
+    }
+}
+
+/// Groups maps the values of the --group-by columns to the stats for their
+/// rows, along with the index of the first row in each group.
+#[derive(Default)]
+struct Groups(HashMap<Vec<Vec<u8>>, (u64, Vec<Stats>)>);
+
+impl Groups {
+    /// Returns the groups in the order that they first appear.
+    fn into_sorted(self) -> Vec<(Vec<Vec<u8>>, Vec<Stats>)> {
+        let mut groups: Vec<_> = self.0.into_iter().collect();
+        groups.sort_by_key(|g| (g.1).0);
+        groups.into_iter().map(|(key, (_, stats))| (key, stats)).collect()
+    }
+}
+
+impl Commute for Groups {
+    fn merge(&mut self, other: Groups) {
+        for (key, (first, stats)) in other.0 {
+            match self.0.entry(key) {
+                Entry::Occupied(mut e) => {
+                    let ours = e.get_mut();
+                    ours.0 = cmp::min(ours.0, first);
+                    ours.1.merge(stats);
+                }
+                Entry::Vacant(e) => { e.insert((first, stats)); }
+            }
+        }
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+/// DateFormats is a list of custom formats for dates and date-times.
+#[derive(Clone, Debug)]
+struct DateFormats(Vec<DateFormat>);
+
+impl<'de> Deserialize<'de> for DateFormats {
+    fn deserialize<D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<DateFormats, D::Error> {
+        let raw = String::deserialize(d)?;
+        raw.split(';')
+           .map(DateFormat::new)
+           .collect::<Result<Vec<_>, _>>()
+           .map(DateFormats)
+           .map_err(D::Error::custom)
+    }
+}
+
+/// Percentiles is a list of percentiles to compute, each between 0 and 100.
+#[derive(Clone, Debug)]
+struct Percentiles(Vec<f64>);
+
+impl<'de> Deserialize<'de> for Percentiles {
+    fn deserialize<D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<Percentiles, D::Error> {
+        let raw = String::deserialize(d)?;
+        let mut percentiles = vec![];
+        for p in raw.split(',') {
+            match p.trim().parse::<f64>() {
+                Ok(n) if n >= 0.0 && n <= 100.0 => percentiles.push(n),
+                _ => {
+                    let msg = format!("Invalid percentile '{}'. Percentiles \
+                                       must be between 0 and 100.", p);
+                    return Err(D::Error::custom(msg));
+                }
+            }
+        }
+        Ok(Percentiles(percentiles))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 struct WhichStats {
     include_nulls: bool,
     sum: bool,
@@ -235,6 +541,11 @@ struct WhichStats {
     dist: bool,
     cardinality: bool,
     median: bool,
+    quartiles: bool,
+    percentiles: Vec<f64>,
+    approx: bool,
+    error_rate: f64,
+    date_formats: Vec<DateFormat>,
     mode: bool,
 }
 
@@ -250,39 +561,54 @@ struct Stats {
     sum: Option<TypedSum>,
     minmax: Option<TypedMinMax>,
     online: Option<OnlineStats>,
+    counts: Counts,
     mode: Option<Unsorted<Vec<u8>>>,
-    median: Option<Unsorted<f64>>,
+    distinct: Option<HyperLogLog>,
+    quantiles: Option<Quantiles>,
     which: WhichStats,
 }
 
 impl Stats {
     fn new(which: WhichStats) -> Stats {
-        let (mut sum, mut minmax, mut online, mut mode, mut median) =
+        let (mut sum, mut minmax, mut online, mut mode, mut quantiles) =
             (None, None, None, None, None);
+        let mut distinct = None;
         if which.sum { sum = Some(Default::default()); }
         if which.range { minmax = Some(Default::default()); }
         if which.dist { online = Some(Default::default()); }
-        if which.mode || which.cardinality { mode = Some(Default::default()); }
-        if which.median { median = Some(Default::default()); }
+        if which.mode || (which.cardinality && !which.approx) {
+            mode = Some(Default::default());
+        }
+        if which.cardinality && which.approx {
+            distinct = Some(HyperLogLog::new(which.error_rate));
+        }
+        if which.median || which.quartiles || !which.percentiles.is_empty() {
+            quantiles = Some(Quantiles::new(which.approx));
+        }
         Stats {
             typ: Default::default(),
             sum: sum,
             minmax: minmax,
             online: online,
+            counts: Default::default(),
             mode: mode,
-            median: median,
+            distinct: distinct,
+            quantiles: quantiles,
             which: which,
         }
     }
 
     fn add(&mut self, sample: &[u8]) {
-        let sample_type = FieldType::from_sample(sample);
+        let (sample_type, date) =
+            FieldType::infer(sample, &self.which.date_formats);
         self.typ.merge(sample_type);
 
         let t = self.typ;
         self.sum.as_mut().map(|v| v.add(t, sample));
-        self.minmax.as_mut().map(|v| v.add(t, sample));
+        self.minmax.as_mut().map(|v| v.add(t, sample, date));
+        self.counts.add(sample);
         self.mode.as_mut().map(|v| v.add(sample.to_vec()));
+        self.distinct.as_mut().map(|v| v.add(sample));
         match self.typ {
             TUnknown => {}
             TNull => {
@@ -290,7 +616,7 @@ impl Stats {
                     self.online.as_mut().map(|v| { v.add_null(); });
                 }
             }
-            TUnicode => {}
+            TUnicode | TDate | TDateTime | TBoolean => {}
             TFloat | TInteger => {
                 if sample_type.is_null() {
                     if self.which.include_nulls {
@@ -298,17 +624,23 @@ impl Stats {
                     }
                 } else {
                     let n = from_bytes::<f64>(sample).unwrap();
-                    self.median.as_mut().map(|v| { v.add(n); });
+                    self.quantiles.as_mut().map(|v| { v.add(n); });
                     self.online.as_mut().map(|v| { v.add(n); });
                 }
             }
         }
     }
 
+    /// The `q` quantile of the numbers in this column, if they were kept.
+    fn quantile(&mut self, q: f64) -> Option<f64> {
+        self.quantiles.as_mut().and_then(|v| v.quantile(q))
+    }
+
     fn to_record(&mut self) -> csv::StringRecord {
         let typ = self.typ;
         let mut pieces = vec![];
         let empty = || "".to_owned();
+        let show = |v: Option<f64>| v.map_or_else(empty, |v| v.to_string());
 
         pieces.push(self.typ.to_string());
         match self.sum.as_ref().and_then(|sum| sum.show(typ)) {
@@ -335,22 +667,14 @@ impl Stats {
                 None => { pieces.push(empty()); pieces.push(empty()); }
             }
         }
-        match self.median.as_mut().and_then(|v| v.median()) {
-            None => {
-                if self.which.median {
-                    pieces.push(empty());
-                }
-            }
-            Some(v) => { pieces.push(v.to_string()); }
+        if self.which.median {
+            pieces.push(show(self.quantile(0.5)));
         }
         match self.mode.as_mut() {
             None => {
                 if self.which.mode {
                     pieces.push(empty());
                 }
-                if self.which.cardinality {
-                    pieces.push(empty());
-                }
             }
             Some(ref mut v) => {
                 if self.which.mode {
@@ -360,11 +684,39 @@ impl Stats {
                     pieces.push(
                         v.mode().map_or("N/A".to_owned(), lossy));
                 }
-                if self.which.cardinality {
-                    pieces.push(v.cardinality().to_string());
-                }
             }
         }
+        if self.which.cardinality {
+            let cardinality = match self.distinct {
+                Some(ref v) => Some(v.estimate()),
+                None => self.mode.as_mut().map(|v| v.cardinality() as u64),
+            };
+            pieces.push(cardinality.map_or_else(empty, |n| n.to_string()));
+        }
+        match self.minmax.as_ref().and_then(|mm| mm.range(typ)) {
+            Some(range) => { pieces.push(range); }
+            None => { pieces.push(empty()); }
+        }
+        match self.counts.mean_length() {
+            Some(len) => { pieces.push(len.to_string()); }
+            None => { pieces.push(empty()); }
+        }
+        pieces.push(self.counts.nulls.to_string());
+        match self.counts.null_percent() {
+            Some(pct) => { pieces.push(pct.to_string()); }
+            None => { pieces.push(empty()); }
+        }
+        pieces.push((self.counts.count - self.counts.nulls).to_string());
+        if self.which.quartiles {
+            let (q1, q3) = (self.quantile(0.25), self.quantile(0.75));
+            pieces.push(show(q1));
+            pieces.push(show(q3));
+            pieces.push(show(q1.and_then(|q1| q3.map(|q3| q3 - q1))));
+        }
+        for i in 0..self.which.percentiles.len() {
+            let p = self.which.percentiles[i];
+            pieces.push(show(self.quantile(p / 100.0)));
+        }
         csv::StringRecord::from(pieces)
     }
 }
@@ -375,36 +727,169 @@ impl Commute for Stats {
         self.sum.merge(other.sum);
         self.minmax.merge(other.minmax);
         self.online.merge(other.online);
+        self.counts.merge(other.counts);
         self.mode.merge(other.mode);
-        self.median.merge(other.median);
+        self.distinct.merge(other.distinct);
+        self.quantiles.merge(other.quantiles);
         self.which.merge(other.which);
     }
 }
 
+/// Counts keeps track of the number of values, how many of them are empty
+/// and their total length.
+#[derive(Clone, Default)]
+struct Counts {
+    count: u64,
+    nulls: u64,
+    total_len: u64,
+}
+
+impl Counts {
+    fn add(&mut self, sample: &[u8]) {
+        self.count += 1;
+        self.total_len += sample.len() as u64;
+        if sample.is_empty() {
+            self.nulls += 1;
+        }
+    }
+
+    fn mean_length(&self) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(self.total_len as f64 / self.count as f64)
+    }
+
+    fn null_percent(&self) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(self.nulls as f64 * 100.0 / self.count as f64)
+    }
+}
+
+impl Commute for Counts {
+    fn merge(&mut self, other: Counts) {
+        self.count += other.count;
+        self.nulls += other.nulls;
+        self.total_len += other.total_len;
+    }
+}
+
+/// Quantiles keeps the numbers seen, so that the median and other
+/// percentiles can be computed from them.
+///
+/// The numbers are either all stored, for exact results, or summarized by a
+/// t-digest, for estimates in constant memory.
+#[derive(Clone)]
+pub enum Quantiles {
+    Exact(Vec<f64>),
+    Approx(TDigest),
+}
+
+impl Quantiles {
+    fn new(approx: bool) -> Quantiles {
+        if approx {
+            Quantiles::Approx(TDigest::new(100.0))
+        } else {
+            Quantiles::Exact(vec![])
+        }
+    }
+
+    fn add(&mut self, sample: f64) {
+        match *self {
+            Quantiles::Exact(ref mut data) => data.push(sample),
+            Quantiles::Approx(ref mut digest) => digest.add(sample),
+        }
+    }
+
+    /// Compute the `q`th quantile, where `q` is between `0` and `1`.
+    pub fn quantile(&mut self, q: f64) -> Option<f64> {
+        match *self {
+            Quantiles::Approx(ref mut digest) => digest.quantile(q),
+            Quantiles::Exact(ref mut data) => {
+                if data.is_empty() {
+                    return None;
+                }
+                data.sort_by(|a, b| {
+                    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+                });
+                let rank = q * (data.len() - 1) as f64;
+                let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+                let frac = rank - lo as f64;
+                Some(data[lo] + (data[hi] - data[lo]) * frac)
+            }
+        }
+    }
+}
+
+impl Commute for Quantiles {
+    fn merge(&mut self, other: Quantiles) {
+        match other {
+            Quantiles::Exact(d2) => {
+                if let Quantiles::Exact(ref mut d1) = *self {
+                    d1.extend(d2);
+                    return;
+                }
+            }
+            Quantiles::Approx(d2) => {
+                if let Quantiles::Approx(ref mut d1) = *self {
+                    d1.merge(d2);
+                    return;
+                }
+            }
+        }
+        unreachable!("merging exact and approximate quantiles");
+    }
+}
+
 #[derive(Clone, Copy, PartialEq)]
-enum FieldType {
+pub enum FieldType {
     TUnknown,
     TNull,
     TUnicode,
     TFloat,
     TInteger,
+    TDate,
+    TDateTime,
+    TBoolean,
 }
 
 impl FieldType {
-    fn from_sample(sample: &[u8]) -> FieldType {
+    pub fn from_sample(sample: &[u8]) -> FieldType {
+        FieldType::infer(sample, &[]).0
+    }
+
+    /// Infer the type of a sample, trying the given date formats after ISO
+    /// 8601. If the sample is a date or date-time, it is also returned.
+    pub fn infer(
+        sample: &[u8],
+        date_formats: &[DateFormat],
+    ) -> (FieldType, Option<DateTime>) {
         if sample.is_empty() {
-            return TNull;
+            return (TNull, None);
         }
         let string = match str::from_utf8(sample) {
-            Err(_) => return TUnknown,
+            Err(_) => return (TUnknown, None),
             Ok(s) => s,
         };
-        if let Ok(_) = string.parse::<i64>() { return TInteger; }
-        if let Ok(_) = string.parse::<f64>() { return TFloat; }
-        TUnicode
+        if let Ok(_) = string.parse::<i64>() { return (TInteger, None); }
+        if let Ok(_) = string.parse::<f64>() { return (TFloat, None); }
+        if string.eq_ignore_ascii_case("true")
+            || string.eq_ignore_ascii_case("false") {
+            return (TBoolean, None);
+        }
+        let date = DateTime::parse_iso(sample).or_else(|| {
+            date_formats.iter().filter_map(|f| f.parse(sample)).next()
+        });
+        match date {
+            Some((dt, false)) => (TDate, Some(dt)),
+            Some((dt, true)) => (TDateTime, Some(dt)),
+            None => (TUnicode, None),
+        }
     }
 
-    fn is_number(&self) -> bool {
+    pub fn is_number(&self) -> bool {
         *self == TFloat || *self == TInteger
     }
 
@@ -419,15 +904,19 @@ impl Commute for FieldType {
             (TUnicode, TUnicode) => TUnicode,
             (TFloat, TFloat) => TFloat,
             (TInteger, TInteger) => TInteger,
+            (TDate, TDate) => TDate,
+            (TDateTime, TDateTime) => TDateTime,
+            (TBoolean, TBoolean) => TBoolean,
             // Null does not impact the type.
             (TNull, any) | (any, TNull) => any,
             // There's no way to get around an unknown.
             (TUnknown, _) | (_, TUnknown) => TUnknown,
             // Integers can degrate to floats.
             (TFloat, TInteger) | (TInteger, TFloat) => TFloat,
-            // Numbers can degrade to Unicode strings.
-            (TUnicode, TFloat) | (TFloat, TUnicode) => TUnicode,
-            (TUnicode, TInteger) | (TInteger, TUnicode) => TUnicode,
+            // Dates can degrade to date-times (at midnight).
+            (TDate, TDateTime) | (TDateTime, TDate) => TDateTime,
+            // Anything else can only be a Unicode string.
+            _ => TUnicode,
         };
     }
 }
@@ -447,6 +936,9 @@ impl fmt::Display for FieldType {
             TUnicode => write!(f, "Unicode"),
             TFloat => write!(f, "Float"),
             TInteger => write!(f, "Integer"),
+            TDate => write!(f, "Date"),
+            TDateTime => write!(f, "DateTime"),
+            TBoolean => write!(f, "Boolean"),
         }
     }
 }
@@ -455,13 +947,13 @@ impl fmt::Display for FieldType {
 ///
 /// It sums integers until it sees a float, at which point it sums floats.
 #[derive(Clone, Default)]
-struct TypedSum {
+pub struct TypedSum {
     integer: i64,
     float: Option<f64>,
 }
 
 impl TypedSum {
-    fn add(&mut self, typ: FieldType, sample: &[u8]) {
+    pub fn add(&mut self, typ: FieldType, sample: &[u8]) {
         if sample.is_empty() {
             return;
         }
@@ -488,9 +980,11 @@ impl TypedSum {
         }
     }
 
-    fn show(&self, typ: FieldType) -> Option<String> {
+    pub fn show(&self, typ: FieldType) -> Option<String> {
         match typ {
-            TNull | TUnicode | TUnknown  => None,
+            TNull | TUnicode | TUnknown | TDate | TDateTime | TBoolean => {
+                None
+            }
             TInteger => Some(self.integer.to_string()),
             TFloat => Some(self.float.unwrap_or(0.0).to_string()),
         }
@@ -510,23 +1004,31 @@ impl Commute for TypedSum {
 
 /// TypedMinMax keeps track of minimum/maximum values for each possible type
 /// where min/max makes sense.
+///
+/// Dates are kept along with the original sample, so that the min/max can be
+/// shown as they were written.
 #[derive(Clone)]
-struct TypedMinMax {
+pub struct TypedMinMax {
     strings: MinMax<Vec<u8>>,
     str_len: MinMax<usize>,
     integers: MinMax<i64>,
     floats: MinMax<f64>,
+    dates: MinMax<(DateTime, Vec<u8>)>,
 }
 
 impl TypedMinMax {
-    fn add(&mut self, typ: FieldType, sample: &[u8]) {
+    pub fn add(&mut self, typ: FieldType, sample: &[u8],
+               date: Option<DateTime>) {
         self.str_len.add(sample.len());
         if sample.is_empty() {
             return;
         }
         self.strings.add(sample.to_vec());
+        if let Some(date) = date {
+            self.dates.add((date, sample.to_vec()));
+        }
         match typ {
-            TUnicode | TUnknown | TNull => {}
+            TUnicode | TUnknown | TNull | TDate | TDateTime | TBoolean => {}
             TFloat => {
                 let n = str::from_utf8(&*sample)
                             .ok()
@@ -553,10 +1055,10 @@ impl TypedMinMax {
         }
     }
 
-    fn show(&self, typ: FieldType) -> Option<(String, String)> {
+    pub fn show(&self, typ: FieldType) -> Option<(String, String)> {
         match typ {
             TNull => None,
-            TUnicode | TUnknown => {
+            TUnicode | TUnknown | TBoolean => {
                 match (self.strings.min(), self.strings.max()) {
                     (Some(min), Some(max)) => {
                         let min = String::from_utf8_lossy(&**min).to_string();
@@ -582,6 +1084,35 @@ impl TypedMinMax {
                     _ => None
                 }
             }
+            TDate | TDateTime => {
+                match (self.dates.min(), self.dates.max()) {
+                    (Some(min), Some(max)) => {
+                        let min = String::from_utf8_lossy(&min.1).to_string();
+                        let max = String::from_utf8_lossy(&max.1).to_string();
+                        Some((min, max))
+                    }
+                    _ => None
+                }
+            }
+        }
+    }
+
+    /// The difference between the max and min, for numbers and dates.
+    pub fn range(&self, typ: FieldType) -> Option<String> {
+        match typ {
+            TInteger => {
+                let (min, max) = (self.integers.min()?, self.integers.max()?);
+                max.checked_sub(*min).map(|n| n.to_string())
+            }
+            TFloat => {
+                let (min, max) = (self.floats.min()?, self.floats.max()?);
+                Some((max - min).to_string())
+            }
+            TDate | TDateTime => {
+                let (min, max) = (self.dates.min()?, self.dates.max()?);
+                Some(min.0.duration_until(&max.0))
+            }
+            TNull | TUnicode | TUnknown | TBoolean => None,
         }
     }
 }
@@ -593,6 +1124,7 @@ impl Default for TypedMinMax {
             str_len: Default::default(),
             integers: Default::default(),
             floats: Default::default(),
+            dates: Default::default(),
         }
     }
 }
@@ -603,6 +1135,7 @@ impl Commute for TypedMinMax {
         self.str_len.merge(other.str_len);
         self.integers.merge(other.integers);
         self.floats.merge(other.floats);
+        self.dates.merge(other.dates);
     }
 }
 
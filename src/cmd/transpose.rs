@@ -0,0 +1,87 @@
+use std::cmp;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use util;
+
+static USAGE: &'static str = "
+Transposes CSV data, so that rows become columns and columns become rows.
+
+The first row (usually the header row) is transposed like any other, so that
+it becomes the first column of the output. Records may have different
+lengths. The output has one row for each field of the longest record, and
+missing fields of shorter records are written as empty fields.
+
+By default, all of the data is read into memory. For large files, use the
+multipass mode (with '--multipass') instead. It reads the input once to find
+the length of the longest record and then once more for each row of output.
+This only keeps one row of output in memory at a time, but the input must be
+a file and not stdin.
+
+Usage:
+    xsv transpose [options] [<input>]
+
+transpose options:
+    -m, --multipass        Re-read the input for each row of output instead
+                           of reading all of it into memory.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character. (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_multipass: bool,
+    flag_output: Option<String>,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let config = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(true)
+        .flexible(true);
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+
+    if args.flag_multipass {
+        if config.is_std() {
+            return fail!("<stdin> cannot be used with --multipass. \
+                          Please specify a file path.");
+        }
+        let mut maxlen = 0;
+        let mut rdr = config.reader()?;
+        let mut record = csv::ByteRecord::new();
+        while rdr.read_byte_record(&mut record)? {
+            maxlen = cmp::max(maxlen, record.len());
+        }
+        let mut row = csv::ByteRecord::new();
+        for i in 0..maxlen {
+            row.clear();
+            let mut rdr = config.reader()?;
+            while rdr.read_byte_record(&mut record)? {
+                row.push_field(record.get(i).unwrap_or(b""));
+            }
+            wtr.write_byte_record(&row)?;
+        }
+    } else {
+        let mut rdr = config.reader()?;
+        let all = rdr.byte_records().collect::<Result<Vec<_>, _>>()?;
+        let maxlen = all.iter().map(|r| r.len()).max().unwrap_or(0);
+        let mut row = csv::ByteRecord::new();
+        for i in 0..maxlen {
+            row.clear();
+            for record in &all {
+                row.push_field(record.get(i).unwrap_or(b""));
+            }
+            wtr.write_byte_record(&row)?;
+        }
+    }
+    Ok(wtr.flush()?)
+}
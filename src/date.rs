@@ -0,0 +1,321 @@
+/// DateTime is a point in time parsed from an ISO 8601 date or date-time.
+///
+/// Values are ordered chronologically. A date without a time is treated as
+/// midnight. Times with a UTC offset are converted to UTC, while times
+/// without one are assumed to already be in UTC.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct DateTime {
+    /// Seconds since the Unix epoch.
+    secs: i64,
+    nanos: u32,
+}
+
+impl DateTime {
+    /// Parse a date (e.g., `2018-03-01`) or a date-time (e.g.,
+    /// `2018-03-01T12:30:00Z`).
+    ///
+    /// The time may be separated from the date by a `T` or a space, the
+    /// seconds are optional and may have a fraction, and the time may end
+    /// with `Z` or an offset like `+05:30`, `+0530` or `+05`.
+    pub fn parse(s: &[u8]) -> Option<DateTime> {
+        DateTime::parse_iso(s).map(|(dt, _)| dt)
+    }
+
+    /// Parse a date or date-time like `parse`, and also return whether it
+    /// had a time.
+    pub fn parse_iso(s: &[u8]) -> Option<(DateTime, bool)> {
+        let mut p = Cursor { s: s, pos: 0 };
+        let year = p.digits(4)?;
+        p.expect(b'-')?;
+        let month = p.digits(2)?;
+        p.expect(b'-')?;
+        let day = p.digits(2)?;
+        if p.done() {
+            return Some((DateTime::from_parts(year, month, day, 0, 0, 0)?,
+                         false));
+        }
+        if !p.eat(b'T') && !p.eat(b' ') {
+            return None;
+        }
+        let hour = p.digits(2)?;
+        p.expect(b':')?;
+        let minute = p.digits(2)?;
+        let second = if p.eat(b':') { p.digits(2)? } else { 0 };
+        let mut dt =
+            DateTime::from_parts(year, month, day, hour, minute, second)?;
+        if p.eat(b'.') || p.eat(b',') {
+            dt.nanos = p.fraction()?;
+        }
+        if p.eat(b'Z') {
+            // Already UTC.
+        } else if p.peek() == Some(b'+') || p.peek() == Some(b'-') {
+            let sign = if p.eat(b'+') { 1 } else { p.expect(b'-')?; -1 };
+            let hours = p.digits(2)?;
+            let minutes = if p.done() {
+                0
+            } else {
+                p.eat(b':');
+                p.digits(2)?
+            };
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            dt.secs -= sign * (hours * 3600 + minutes * 60);
+        }
+        if p.done() { Some((dt, true)) } else { None }
+    }
+
+    /// Create a date-time from its parts, or `None` if any of them is out
+    /// of range.
+    fn from_parts(
+        year: i64,
+        month: i64,
+        day: i64,
+        hour: i64,
+        minute: i64,
+        second: i64,
+    ) -> Option<DateTime> {
+        if month < 1 || month > 12
+            || day < 1 || day > days_in_month(year, month)
+            || hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        let days = days_from_civil(year, month, day);
+        Some(DateTime {
+            secs: days * 86400 + hour * 3600 + minute * 60 + second,
+            nanos: 0,
+        })
+    }
+
+    /// Format the time from `self` until `later` as an ISO 8601 duration,
+    /// e.g., `P3DT4H5M6S`.
+    pub fn duration_until(&self, later: &DateTime) -> String {
+        let (mut secs, mut nanos) = (later.secs - self.secs, later.nanos);
+        if nanos < self.nanos {
+            secs -= 1;
+            nanos += 1_000_000_000;
+        }
+        nanos -= self.nanos;
+
+        let (days, secs) = (secs / 86400, secs % 86400);
+        let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+        let mut dur = "P".to_owned();
+        if days > 0 {
+            dur.push_str(&format!("{}D", days));
+        }
+        if hours > 0 || minutes > 0 || secs > 0 || nanos > 0 || days == 0 {
+            dur.push('T');
+            if hours > 0 {
+                dur.push_str(&format!("{}H", hours));
+            }
+            if minutes > 0 {
+                dur.push_str(&format!("{}M", minutes));
+            }
+            if secs > 0 || nanos > 0 || (hours == 0 && minutes == 0) {
+                dur.push_str(&secs.to_string());
+                if nanos > 0 {
+                    let frac = format!("{:09}", nanos);
+                    dur.push('.');
+                    dur.push_str(frac.trim_right_matches('0'));
+                }
+                dur.push('S');
+            }
+        }
+        dur
+    }
+}
+
+/// DateFormat is a custom format for dates and date-times.
+///
+/// Formats are written with `strftime`-style specifiers: `%Y` is a four
+/// digit year, `%m` a month, `%b` an abbreviated month name (e.g., `Jan`),
+/// `%d` a day, `%H` an hour from 0 to 23, `%M` a minute, `%S` a second and
+/// `%%` a literal `%`. Except for the year, numbers may have one or two
+/// digits. Everything else must match exactly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DateFormat {
+    items: Vec<Item>,
+    has_time: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Item {
+    Literal(u8),
+    Year,
+    Month,
+    MonthName,
+    Day,
+    Hour,
+    Minute,
+    Second,
+}
+
+static MONTH_NAMES: [&'static [u8]; 12] = [
+    b"jan", b"feb", b"mar", b"apr", b"may", b"jun",
+    b"jul", b"aug", b"sep", b"oct", b"nov", b"dec",
+];
+
+impl DateFormat {
+    pub fn new(fmt: &str) -> Result<DateFormat, String> {
+        let mut items = vec![];
+        let mut bytes = fmt.bytes();
+        while let Some(b) = bytes.next() {
+            if b != b'%' {
+                items.push(Item::Literal(b));
+                continue;
+            }
+            items.push(match bytes.next() {
+                Some(b'Y') => Item::Year,
+                Some(b'm') => Item::Month,
+                Some(b'b') => Item::MonthName,
+                Some(b'd') => Item::Day,
+                Some(b'H') => Item::Hour,
+                Some(b'M') => Item::Minute,
+                Some(b'S') => Item::Second,
+                Some(b'%') => Item::Literal(b'%'),
+                _ => {
+                    return Err(format!(
+                        "Invalid date format '{}'. Only %Y, %m, %b, %d, \
+                         %H, %M, %S and %% are supported.", fmt));
+                }
+            });
+        }
+        let has_time = {
+            let has = |item| items.contains(&item);
+            let has_month = has(Item::Month) || has(Item::MonthName);
+            if !has(Item::Year) || !has_month || !has(Item::Day) {
+                return Err(format!(
+                    "Invalid date format '{}'. It must have a year, a month \
+                     and a day.", fmt));
+            }
+            has(Item::Hour)
+        };
+        Ok(DateFormat { items: items, has_time: has_time })
+    }
+
+    /// Parse a date or date-time in this format, and also return whether
+    /// the format has a time.
+    pub fn parse(&self, s: &[u8]) -> Option<(DateTime, bool)> {
+        let mut p = Cursor { s: s, pos: 0 };
+        let (mut year, mut month, mut day) = (0, 0, 0);
+        let (mut hour, mut minute, mut second) = (0, 0, 0);
+        for &item in &self.items {
+            match item {
+                Item::Literal(b) => p.expect(b)?,
+                Item::Year => year = p.digits(4)?,
+                Item::Month => month = p.one_or_two_digits()?,
+                Item::MonthName => month = p.month_name()?,
+                Item::Day => day = p.one_or_two_digits()?,
+                Item::Hour => hour = p.one_or_two_digits()?,
+                Item::Minute => minute = p.one_or_two_digits()?,
+                Item::Second => second = p.one_or_two_digits()?,
+            }
+        }
+        if !p.done() {
+            return None;
+        }
+        let dt = DateTime::from_parts(year, month, day, hour, minute, second)?;
+        Some((dt, self.has_time))
+    }
+}
+
+struct Cursor<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn done(&self) -> bool {
+        self.pos == self.s.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        if self.peek() == Some(b) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, b: u8) -> Option<()> {
+        if self.eat(b) { Some(()) } else { None }
+    }
+
+    /// Read exactly `n` ASCII digits as a number.
+    fn digits(&mut self, n: usize) -> Option<i64> {
+        let mut num = 0;
+        for _ in 0..n {
+            match self.peek() {
+                Some(b) if b.is_ascii_digit() => {
+                    num = num * 10 + (b - b'0') as i64;
+                }
+                _ => return None,
+            }
+            self.pos += 1;
+        }
+        Some(num)
+    }
+
+    /// Read a number with one or two ASCII digits.
+    fn one_or_two_digits(&mut self) -> Option<i64> {
+        let first = self.digits(1)?;
+        match self.peek() {
+            Some(b) if b.is_ascii_digit() => {
+                self.pos += 1;
+                Some(first * 10 + (b - b'0') as i64)
+            }
+            _ => Some(first),
+        }
+    }
+
+    /// Read an abbreviated English month name, ignoring case.
+    fn month_name(&mut self) -> Option<i64> {
+        let name = self.s.get(self.pos..self.pos + 3)?.to_ascii_lowercase();
+        let month = MONTH_NAMES.iter().position(|&m| m == &*name)?;
+        self.pos += 3;
+        Some(month as i64 + 1)
+    }
+
+    /// Read one or more digits of a fraction of a second as nanoseconds.
+    /// Digits past nanosecond precision are ignored.
+    fn fraction(&mut self) -> Option<u32> {
+        let (mut nanos, mut scale, start) = (0, 100_000_000, self.pos);
+        while let Some(b) = self.peek().filter(|b| b.is_ascii_digit()) {
+            nanos += (b - b'0') as u32 * scale;
+            scale /= 10;
+            self.pos += 1;
+        }
+        if self.pos == start { None } else { Some(nanos) }
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// The number of days between the Unix epoch and the given date in the
+/// proleptic Gregorian calendar.
+///
+/// This is Howard Hinnant's `days_from_civil` algorithm.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
@@ -0,0 +1,869 @@
+use std::cmp::{self, Ordering};
+use std::fmt;
+use std::str::{self, FromStr};
+
+use csv;
+use regex::Regex;
+
+use cmd::stats::FieldType;
+use select::SelectColumns;
+
+/// Value is the result of evaluating an expression.
+///
+/// Fields read from CSV data are typed the same way that `xsv stats` infers
+/// types: empty fields are null, and fields that parse as numbers are
+/// integers or floats. Everything else is a string.
+///
+/// A number read from a field keeps the field's text, since e.g. `007` and
+/// `1.50` would change if they were formatted again. Arithmetic and
+/// comparisons use the number, while string functions and output use the
+/// text.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Field(Box<Value>, String),
+}
+
+impl Value {
+    pub fn from_field(field: &[u8]) -> Value {
+        let num = match FieldType::from_sample(field) {
+            FieldType::TNull => return Value::Null,
+            FieldType::TInteger => {
+                Value::Int(from_bytes::<i64>(field).unwrap())
+            }
+            FieldType::TFloat => {
+                Value::Float(from_bytes::<f64>(field).unwrap())
+            }
+            FieldType::TUnicode | FieldType::TUnknown | FieldType::TDate
+            | FieldType::TDateTime | FieldType::TBoolean => {
+                return Value::Str(
+                    String::from_utf8_lossy(field).into_owned());
+            }
+        };
+        // Numbers are always ASCII, so the field is valid UTF-8.
+        let text = String::from_utf8_lossy(field).into_owned();
+        Value::Field(Box::new(num), text)
+    }
+
+    /// The value used for arithmetic and comparisons, which is the number
+    /// rather than the text of a numeric field.
+    fn typed(&self) -> &Value {
+        match *self {
+            Value::Field(ref num, _) => num,
+            ref v => v,
+        }
+    }
+
+    /// Whether this value counts as true in a boolean context.
+    ///
+    /// Nulls, `false`, zero and the empty string are false. Everything else
+    /// is true.
+    pub fn is_truthy(&self) -> bool {
+        match *self.typed() {
+            Value::Null => false,
+            Value::Bool(b) => b,
+            Value::Int(n) => n != 0,
+            Value::Float(n) => n != 0.0,
+            Value::Str(ref s) => !s.is_empty(),
+            Value::Field(_, _) => unreachable!(),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+
+    fn as_int(&self) -> Option<i64> {
+        match *self.typed() {
+            Value::Int(n) => Some(n),
+            Value::Float(n) if n.fract() == 0.0 => Some(n as i64),
+            _ => None,
+        }
+    }
+
+    fn as_float(&self) -> Option<f64> {
+        match *self.typed() {
+            Value::Int(n) => Some(n as f64),
+            Value::Float(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Compare two non-null values.
+    ///
+    /// Numbers are compared numerically and booleans with booleans. Any
+    /// other combination is compared by the string form of each value.
+    fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self.typed(), other.typed()) {
+            (&Value::Null, _) | (_, &Value::Null) => None,
+            (&Value::Int(a), &Value::Int(b)) => Some(a.cmp(&b)),
+            (&Value::Bool(a), &Value::Bool(b)) => Some(a.cmp(&b)),
+            _ => match (self.as_float(), other.as_float()) {
+                (Some(a), Some(b)) => a.partial_cmp(&b),
+                _ => Some(self.to_string().cmp(&other.to_string())),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Null => Ok(()),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Float(n) => write!(f, "{}", n),
+            Value::Str(ref s) | Value::Field(_, ref s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// Expr is a compiled expression whose column references have been resolved
+/// against a header row.
+#[derive(Clone, Debug)]
+pub enum Expr {
+    Literal(Value),
+    Column(usize),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Cmp(CmpOp, Box<Expr>, Box<Expr>),
+    Arith(ArithOp, Box<Expr>, Box<Expr>),
+    Match(Box<Expr>, Regex),
+    Call(Func, Vec<Expr>),
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CmpOp { Eq, Ne, Lt, Le, Gt, Ge }
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ArithOp { Add, Sub, Mul, Div, Rem }
+
+/// The built in functions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Func {
+    Len,
+    Lower,
+    Upper,
+    Trim,
+    Contains,
+    StartsWith,
+    EndsWith,
+    IsNull,
+    Substr,
+    Concat,
+    Replace,
+    If,
+    Coalesce,
+    Abs,
+    Round,
+    Floor,
+    Ceil,
+}
+
+impl Func {
+    /// Look up a function by name, along with the minimum and maximum number
+    /// of arguments it takes.
+    fn from_name(name: &str) -> Option<(Func, usize, usize)> {
+        let many = ::std::usize::MAX;
+        Some(match name {
+            "len" => (Func::Len, 1, 1),
+            "lower" => (Func::Lower, 1, 1),
+            "upper" => (Func::Upper, 1, 1),
+            "trim" => (Func::Trim, 1, 1),
+            "contains" => (Func::Contains, 2, 2),
+            "starts_with" => (Func::StartsWith, 2, 2),
+            "ends_with" => (Func::EndsWith, 2, 2),
+            "is_null" => (Func::IsNull, 1, 1),
+            "substr" => (Func::Substr, 2, 3),
+            "concat" => (Func::Concat, 1, many),
+            "replace" => (Func::Replace, 3, 3),
+            "if" => (Func::If, 2, 3),
+            "coalesce" => (Func::Coalesce, 1, many),
+            "abs" => (Func::Abs, 1, 1),
+            "round" => (Func::Round, 1, 2),
+            "floor" => (Func::Floor, 1, 1),
+            "ceil" => (Func::Ceil, 1, 1),
+            _ => return None,
+        })
+    }
+
+    /// Call this function on already evaluated arguments.
+    ///
+    /// Unless a function says otherwise, it returns null if any of its
+    /// arguments are null. (`if` and `coalesce` are evaluated lazily by
+    /// `Expr::eval` instead.)
+    fn call(self, args: Vec<Value>) -> Value {
+        if self != Func::IsNull && self != Func::Concat
+                && args.iter().any(|v| v.is_null()) {
+            return Value::Null;
+        }
+        let s = |i: usize| args[i].to_string();
+        match self {
+            Func::IsNull => Value::Bool(args[0].is_null()),
+            Func::Concat => {
+                Value::Str(args.iter().map(|v| v.to_string()).collect())
+            }
+            Func::If | Func::Coalesce => unreachable!(),
+            Func::Len => Value::Int(s(0).chars().count() as i64),
+            Func::Lower => Value::Str(s(0).to_lowercase()),
+            Func::Upper => Value::Str(s(0).to_uppercase()),
+            Func::Trim => Value::Str(s(0).trim().to_owned()),
+            Func::Contains => Value::Bool(s(0).contains(&*s(1))),
+            Func::StartsWith => Value::Bool(s(0).starts_with(&*s(1))),
+            Func::EndsWith => Value::Bool(s(0).ends_with(&*s(1))),
+            Func::Replace => Value::Str(s(0).replace(&*s(1), &s(2))),
+            Func::Substr => {
+                let len = if args.len() > 2 { args[2].as_int() } else { None };
+                substr(&s(0), args[1].as_int(), len, args.len() > 2)
+            }
+            Func::Abs => match *args[0].typed() {
+                Value::Int(n) => n.checked_abs().map_or(Value::Null, Value::Int),
+                Value::Float(n) => Value::Float(n.abs()),
+                _ => Value::Null,
+            },
+            Func::Round => {
+                let digits =
+                    if args.len() > 1 { args[1].as_int() } else { Some(0) };
+                match (args[0].as_float(), digits) {
+                    (Some(n), Some(d)) if d > 0 => {
+                        let scale = 10f64.powi(d as i32);
+                        Value::Float((n * scale).round() / scale)
+                    }
+                    (Some(n), Some(_)) => float_to_int(n.round()),
+                    _ => Value::Null,
+                }
+            }
+            Func::Floor => args[0].as_float().map_or(Value::Null, |n| {
+                float_to_int(n.floor())
+            }),
+            Func::Ceil => args[0].as_float().map_or(Value::Null, |n| {
+                float_to_int(n.ceil())
+            }),
+        }
+    }
+}
+
+/// The substring of `s` starting at character `start` and containing at most
+/// `len` characters. A negative `start` counts back from the end of `s`.
+fn substr(s: &str, start: Option<i64>, len: Option<i64>, has_len: bool)
+         -> Value {
+    let nchars = s.chars().count() as i64;
+    let start = match start {
+        None => return Value::Null,
+        Some(i) if i < 0 => cmp::max(0, nchars + i),
+        Some(i) => i,
+    };
+    let len = match (has_len, len) {
+        (false, _) => nchars,
+        (true, Some(n)) => cmp::max(0, n),
+        (true, None) => return Value::Null,
+    };
+    Value::Str(s.chars().skip(start as usize).take(len as usize).collect())
+}
+
+/// Convert a whole number stored as a float to an integer if it fits.
+fn float_to_int(n: f64) -> Value {
+    if n >= (::std::i64::MIN as f64) && n < (::std::i64::MAX as f64) {
+        Value::Int(n as i64)
+    } else {
+        Value::Float(n)
+    }
+}
+
+impl Expr {
+    /// Parse and compile `src`, resolving column names against `headers`.
+    pub fn parse(
+        src: &str,
+        headers: &csv::ByteRecord,
+        use_names: bool,
+    ) -> Result<Expr, String> {
+        compile(tokenize(src)?, headers, use_names)
+    }
+
+    /// Evaluate this expression against a single record.
+    pub fn eval(&self, record: &csv::ByteRecord) -> Value {
+        match *self {
+            Expr::Literal(ref v) => v.clone(),
+            Expr::Column(i) => {
+                record.get(i).map_or(Value::Null, Value::from_field)
+            }
+            Expr::Not(ref e) => Value::Bool(!e.eval(record).is_truthy()),
+            Expr::Neg(ref e) => match *e.eval(record).typed() {
+                Value::Int(n) => match n.checked_neg() {
+                    None => Value::Float(-(n as f64)),
+                    Some(n) => Value::Int(n),
+                },
+                Value::Float(n) => Value::Float(-n),
+                _ => Value::Null,
+            },
+            Expr::And(ref a, ref b) => Value::Bool(
+                a.eval(record).is_truthy() && b.eval(record).is_truthy()),
+            Expr::Or(ref a, ref b) => Value::Bool(
+                a.eval(record).is_truthy() || b.eval(record).is_truthy()),
+            Expr::Cmp(op, ref a, ref b) => {
+                let (a, b) = (a.eval(record), b.eval(record));
+                Value::Bool(op.apply(&a, &b))
+            }
+            Expr::Arith(op, ref a, ref b) => {
+                op.apply(&a.eval(record), &b.eval(record))
+            }
+            Expr::Match(ref e, ref re) => match e.eval(record) {
+                Value::Null => Value::Bool(false),
+                v => Value::Bool(re.is_match(&v.to_string())),
+            },
+            Expr::Call(Func::If, ref args) => {
+                if args[0].eval(record).is_truthy() {
+                    args[1].eval(record)
+                } else {
+                    args.get(2).map_or(Value::Null, |e| e.eval(record))
+                }
+            }
+            Expr::Call(Func::Coalesce, ref args) => {
+                for e in args {
+                    let v = e.eval(record);
+                    if !v.is_null() {
+                        return v;
+                    }
+                }
+                Value::Null
+            }
+            Expr::Call(func, ref args) => {
+                func.call(args.iter().map(|e| e.eval(record)).collect())
+            }
+        }
+    }
+}
+
+/// Definition is a `name = expression` assignment, as used by `xsv map`.
+///
+/// The expression isn't compiled until `compile` is called, since it may
+/// refer to columns created by earlier definitions.
+#[derive(Clone, Debug)]
+pub struct Definition {
+    /// The name of the column being defined (or its index, if `by_index`).
+    pub name: String,
+    /// The `xsv select` syntax for the existing column this replaces.
+    column: String,
+    by_index: bool,
+    tokens: Vec<Token>,
+}
+
+impl Definition {
+    /// Parse a list of definitions separated by semicolons.
+    pub fn parse_all(src: &str) -> Result<Vec<Definition>, String> {
+        let mut defs = vec![];
+        for stmt in tokenize(src)?.split(|t| *t == Token::Op(";")) {
+            if stmt.is_empty() {
+                continue;
+            }
+            let (name, column, by_index) = match stmt[0] {
+                Token::Ident(ref name) => (name.clone(), name.clone(), false),
+                Token::Column(ref name, nth) => {
+                    (name.clone(), quote_name(name, nth), false)
+                }
+                Token::Index(ref idx) => (idx.clone(), idx.clone(), true),
+                ref tok => {
+                    return Err(format!(
+                        "Expected a column name but got {}.", tok));
+                }
+            };
+            if stmt.get(1) != Some(&Token::Op("=")) || stmt.len() < 3 {
+                return Err(format!(
+                    "Expected a definition of the form '{} = <expression>'.",
+                    name));
+            }
+            defs.push(Definition {
+                name: name,
+                column: column,
+                by_index: by_index,
+                tokens: stmt[2..].to_vec(),
+            });
+        }
+        if defs.is_empty() {
+            return Err("No column definitions were given.".to_owned());
+        }
+        Ok(defs)
+    }
+
+    /// Compile the expression of this definition.
+    pub fn compile(
+        &self,
+        headers: &csv::ByteRecord,
+        use_names: bool,
+    ) -> Result<Expr, String> {
+        compile(self.tokens.clone(), headers, use_names)
+    }
+
+    /// The index of the existing column that this definition replaces, or
+    /// `None` if it defines a new column.
+    pub fn target(
+        &self,
+        headers: &csv::ByteRecord,
+        use_names: bool,
+    ) -> Result<Option<usize>, String> {
+        match resolve_column(&self.column, headers, use_names) {
+            Ok(i) => Ok(Some(i)),
+            Err(err) => if self.by_index { Err(err) } else { Ok(None) },
+        }
+    }
+}
+
+impl CmpOp {
+    /// Apply this comparison. Nulls are only equal to other nulls, and are
+    /// never less or greater than anything.
+    fn apply(self, a: &Value, b: &Value) -> bool {
+        if a.is_null() || b.is_null() {
+            return match self {
+                CmpOp::Eq => a.is_null() && b.is_null(),
+                CmpOp::Ne => a.is_null() != b.is_null(),
+                _ => false,
+            };
+        }
+        let ord = match a.compare(b) {
+            None => return self == CmpOp::Ne,
+            Some(ord) => ord,
+        };
+        match self {
+            CmpOp::Eq => ord == Ordering::Equal,
+            CmpOp::Ne => ord != Ordering::Equal,
+            CmpOp::Lt => ord == Ordering::Less,
+            CmpOp::Le => ord != Ordering::Greater,
+            CmpOp::Gt => ord == Ordering::Greater,
+            CmpOp::Ge => ord != Ordering::Less,
+        }
+    }
+}
+
+impl ArithOp {
+    /// Apply this arithmetic operator. Integer arithmetic is used when both
+    /// sides are integers and the result is exact; otherwise floats are used.
+    /// Anything that isn't a number (and division by zero) yields null.
+    fn apply(self, a: &Value, b: &Value) -> Value {
+        if let (&Value::Int(x), &Value::Int(y)) = (a.typed(), b.typed()) {
+            let exact = match self {
+                ArithOp::Add => x.checked_add(y),
+                ArithOp::Sub => x.checked_sub(y),
+                ArithOp::Mul => x.checked_mul(y),
+                ArithOp::Div if x.checked_rem(y) == Some(0) => {
+                    x.checked_div(y)
+                }
+                ArithOp::Rem => x.checked_rem(y),
+                ArithOp::Div => None,
+            };
+            if let Some(n) = exact {
+                return Value::Int(n);
+            }
+        }
+        let (x, y) = match (a.as_float(), b.as_float()) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return Value::Null,
+        };
+        match self {
+            ArithOp::Add => Value::Float(x + y),
+            ArithOp::Sub => Value::Float(x - y),
+            ArithOp::Mul => Value::Float(x * y),
+            ArithOp::Div | ArithOp::Rem if y == 0.0 => Value::Null,
+            ArithOp::Div => Value::Float(x / y),
+            ArithOp::Rem => Value::Float(x % y),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    /// A backticked column name, and which of the columns with that name it
+    /// refers to.
+    Column(String, usize),
+    Index(String),
+    Str(String),
+    Num(Value),
+    Op(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Ident(ref s) => write!(f, "'{}'", s),
+            Token::Column(ref s, _) => write!(f, "column '{}'", s),
+            Token::Index(ref s) => write!(f, "column #{}", s),
+            Token::Str(ref s) => write!(f, "string {:?}", s),
+            Token::Num(ref n) => write!(f, "number {}", n),
+            Token::Op(op) => write!(f, "'{}'", op),
+        }
+    }
+}
+
+/// Operators, longest first so that e.g. `<=` isn't lexed as `<`.
+static OPERATORS: &'static [&'static str] = &[
+    "&&", "||", "==", "!=", "<=", ">=", "=~", "!~",
+    "<", ">", "!", "+", "-", "*", "/", "%", "(", ")", ",", "=", ";",
+];
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    'outer: while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '"' || c == '\'' {
+            let (s, end) = lex_quoted(&chars, i)?;
+            tokens.push(Token::Str(s));
+            i = end;
+        } else if c == '`' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i] != '`' {
+                i += 1;
+            }
+            if i == chars.len() {
+                return Err("Unclosed '`' in expression.".to_owned());
+            }
+            let name = chars[start..i].iter().collect();
+            i += 1;
+            let mut nth = 0;
+            if i < chars.len() && chars[i] == '[' {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                if i == start || i == chars.len() || chars[i] != ']' {
+                    return Err("Expected a number in brackets after a \
+                                column name.".to_owned());
+                }
+                let digits: String = chars[start..i].iter().collect();
+                nth = digits.parse().map_err(|_| {
+                    format!("Invalid column position '{}'.", digits)
+                })?;
+                i += 1;
+            }
+            tokens.push(Token::Column(name, nth));
+        } else if c == '#' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            if i == start {
+                return Err("Expected a column number after '#'.".to_owned());
+            }
+            tokens.push(Token::Index(chars[start..i].iter().collect()));
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len()
+                  && (chars[i].is_ascii_alphanumeric() || chars[i] == '.'
+                      || ((chars[i] == '-' || chars[i] == '+')
+                          && (chars[i-1] == 'e' || chars[i-1] == 'E'))) {
+                i += 1;
+            }
+            let s: String = chars[start..i].iter().collect();
+            let num = if let Ok(n) = s.parse::<i64>() {
+                Value::Int(n)
+            } else if let Ok(n) = s.parse::<f64>() {
+                Value::Float(n)
+            } else {
+                return Err(format!("Invalid number '{}' in expression.", s));
+            };
+            tokens.push(Token::Num(num));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                  && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            for op in OPERATORS {
+                let len = op.chars().count();
+                if chars[i..].iter().take(len).cloned().eq(op.chars()) {
+                    tokens.push(Token::Op(op));
+                    i += len;
+                    continue 'outer;
+                }
+            }
+            return Err(format!("Unexpected character '{}' in expression.", c));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Lex a string literal starting at `chars[start]`, which is the opening
+/// quote. Returns the string and the position just after the closing quote.
+fn lex_quoted(chars: &[char], start: usize) -> Result<(String, usize), String> {
+    let quote = chars[start];
+    let mut s = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            c if c == quote => return Ok((s, i + 1)),
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                s.push(match chars[i] {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    c => c,
+                });
+            }
+            c => s.push(c),
+        }
+        i += 1;
+    }
+    Err(format!("Unclosed {} in expression.", quote))
+}
+
+fn compile(
+    tokens: Vec<Token>,
+    headers: &csv::ByteRecord,
+    use_names: bool,
+) -> Result<Expr, String> {
+    let mut p = Parser {
+        tokens: tokens,
+        pos: 0,
+        headers: headers,
+        use_names: use_names,
+    };
+    let expr = p.parse_expr()?;
+    match p.cur() {
+        None => Ok(expr),
+        Some(tok) => Err(format!("Unexpected {} in expression.", tok)),
+    }
+}
+
+/// The `xsv select` syntax for the `nth` column named exactly `name`, so
+/// that e.g. `order-id` isn't read as a range or `2019` as an index.
+fn quote_name(name: &str, nth: usize) -> String {
+    format!("\"{}\"[{}]", name, nth)
+}
+
+/// Resolve a column reference using the same rules as `xsv select`.
+fn resolve_column(
+    name: &str,
+    headers: &csv::ByteRecord,
+    use_names: bool,
+) -> Result<usize, String> {
+    let sel = SelectColumns::parse(name)?.selection(headers, use_names)?;
+    if sel.len() != 1 {
+        return Err(format!(
+            "Column reference '{}' must select exactly one column.", name));
+    }
+    Ok(sel[0])
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    headers: &'a csv::ByteRecord,
+    use_names: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn cur(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn bump(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    /// If the current token is one of the operators in `ops`, consume and
+    /// return it.
+    fn eat_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        let found = match self.cur() {
+            Some(&Token::Op(op)) if ops.contains(&op) => op,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(found)
+    }
+
+    fn expect_op(&mut self, op: &'static str) -> Result<(), String> {
+        if self.eat_op(&[op]).is_some() {
+            return Ok(());
+        }
+        match self.cur() {
+            None => Err(format!(
+                "Expected '{}' but reached the end of the expression.", op)),
+            Some(tok) => Err(format!("Expected '{}' but got {}.", op, tok)),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, String> {
+        self.parse_or()
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut e = self.parse_and()?;
+        while self.eat_op(&["||"]).is_some() {
+            e = Expr::Or(Box::new(e), Box::new(self.parse_and()?));
+        }
+        Ok(e)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut e = self.parse_cmp()?;
+        while self.eat_op(&["&&"]).is_some() {
+            e = Expr::And(Box::new(e), Box::new(self.parse_cmp()?));
+        }
+        Ok(e)
+    }
+
+    fn parse_cmp(&mut self) -> Result<Expr, String> {
+        let e = self.parse_additive()?;
+        let op = match self.eat_op(&["==", "!=", "<", "<=", ">", ">=",
+                                     "=~", "!~"]) {
+            None => return Ok(e),
+            Some(op) => op,
+        };
+        if op == "=~" || op == "!~" {
+            let m = Expr::Match(Box::new(e), self.parse_regex()?);
+            return Ok(if op == "!~" { Expr::Not(Box::new(m)) } else { m });
+        }
+        let cmp = match op {
+            "==" => CmpOp::Eq,
+            "!=" => CmpOp::Ne,
+            "<" => CmpOp::Lt,
+            "<=" => CmpOp::Le,
+            ">" => CmpOp::Gt,
+            _ => CmpOp::Ge,
+        };
+        Ok(Expr::Cmp(cmp, Box::new(e), Box::new(self.parse_additive()?)))
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, String> {
+        let mut e = self.parse_multiplicative()?;
+        while let Some(op) = self.eat_op(&["+", "-"]) {
+            let op = if op == "+" { ArithOp::Add } else { ArithOp::Sub };
+            let rhs = self.parse_multiplicative()?;
+            e = Expr::Arith(op, Box::new(e), Box::new(rhs));
+        }
+        Ok(e)
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, String> {
+        let mut e = self.parse_unary()?;
+        while let Some(op) = self.eat_op(&["*", "/", "%"]) {
+            let op = match op {
+                "*" => ArithOp::Mul,
+                "/" => ArithOp::Div,
+                _ => ArithOp::Rem,
+            };
+            let rhs = self.parse_unary()?;
+            e = Expr::Arith(op, Box::new(e), Box::new(rhs));
+        }
+        Ok(e)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        match self.eat_op(&["!", "-"]) {
+            Some("!") => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            Some(_) => Ok(Expr::Neg(Box::new(self.parse_unary()?))),
+            None => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.bump() {
+            None => Err("Unexpected end of expression.".to_owned()),
+            Some(Token::Num(n)) => Ok(Expr::Literal(n)),
+            Some(Token::Str(s)) => Ok(Expr::Literal(Value::Str(s))),
+            Some(Token::Column(name, nth)) => {
+                self.column(&quote_name(&name, nth))
+            }
+            Some(Token::Index(idx)) => self.column(&idx),
+            Some(Token::Op("(")) => {
+                let e = self.parse_expr()?;
+                self.expect_op(")")?;
+                Ok(e)
+            }
+            Some(Token::Ident(name)) => {
+                if self.eat_op(&["("]).is_some() {
+                    return self.parse_call(&name);
+                }
+                match &*name {
+                    "true" => Ok(Expr::Literal(Value::Bool(true))),
+                    "false" => Ok(Expr::Literal(Value::Bool(false))),
+                    "null" => Ok(Expr::Literal(Value::Null)),
+                    _ => self.column(&name),
+                }
+            }
+            Some(tok) => Err(format!("Unexpected {} in expression.", tok)),
+        }
+    }
+
+    /// Parse the arguments of a call to `name`, just after the opening
+    /// parenthesis.
+    fn parse_call(&mut self, name: &str) -> Result<Expr, String> {
+        let mut args = vec![];
+        if self.eat_op(&[")"]).is_none() {
+            loop {
+                args.push(self.parse_expr()?);
+                if self.eat_op(&[")"]).is_some() {
+                    break;
+                }
+                self.expect_op(",")?;
+            }
+        }
+        if name == "matches" {
+            if args.len() != 2 {
+                return Err(format!(
+                    "Function 'matches' takes 2 arguments but was given {}.",
+                    args.len()));
+            }
+            let re = match args.pop() {
+                Some(Expr::Literal(Value::Str(re))) => Regex::new(&re)
+                    .map_err(|err| format!("Invalid regex: {}", err))?,
+                _ => return Err("The second argument to 'matches' must be \
+                                 a string literal.".to_owned()),
+            };
+            return Ok(Expr::Match(Box::new(args.pop().unwrap()), re));
+        }
+        let (func, min, max) = match Func::from_name(name) {
+            None => return Err(format!("Unknown function '{}'.", name)),
+            Some(f) => f,
+        };
+        if args.len() < min || args.len() > max {
+            let arity =
+                if min == max {
+                    min.to_string()
+                } else if max == ::std::usize::MAX {
+                    format!("at least {}", min)
+                } else {
+                    format!("{} to {}", min, max)
+                };
+            return Err(format!(
+                "Function '{}' takes {} argument(s) but was given {}.",
+                name, arity, args.len()));
+        }
+        Ok(Expr::Call(func, args))
+    }
+
+    /// Parse the right hand side of `=~`, which must be a string literal.
+    fn parse_regex(&mut self) -> Result<Regex, String> {
+        match self.bump() {
+            Some(Token::Str(re)) => {
+                Regex::new(&re).map_err(|err| format!("Invalid regex: {}", err))
+            }
+            _ => Err("Expected a string literal regex after '=~'.".to_owned()),
+        }
+    }
+
+    fn column(&self, name: &str) -> Result<Expr, String> {
+        resolve_column(name, self.headers, self.use_names).map(Expr::Column)
+    }
+}
+
+fn from_bytes<T: FromStr>(bytes: &[u8]) -> Option<T> {
+    str::from_utf8(bytes).ok().and_then(|s| s.parse().ok())
+}
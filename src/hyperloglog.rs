@@ -0,0 +1,83 @@
+use std::cmp;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+
+use stats::Commute;
+
+/// The smallest relative standard error that a sketch supports. This is the
+/// error of the largest precision, `1.04 / sqrt(2^18)`, rounded up.
+pub const MIN_ERROR: f64 = 0.00204;
+
+/// HyperLogLog estimates the number of distinct values in a stream.
+///
+/// This is the algorithm from Flajolet et al., "HyperLogLog: the analysis
+/// of a near-optimal cardinality estimation algorithm", using a 64 bit hash
+/// and linear counting for small cardinalities. A sketch with precision `p`
+/// uses `2^p` one byte registers and has a relative standard error of about
+/// `1.04 / sqrt(2^p)`. Sketches with the same precision can be merged.
+#[derive(Clone, Debug)]
+pub struct HyperLogLog {
+    precision: u32,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// Create a sketch with a relative standard error of at most `error`.
+    ///
+    /// The precision is kept between 4 and 18, so very large errors are
+    /// lowered to about 26%. Callers must reject errors below `MIN_ERROR`,
+    /// which would otherwise be raised to it.
+    pub fn new(error: f64) -> HyperLogLog {
+        let mut precision = 4;
+        while precision < 18
+                && 1.04 / ((1u64 << precision) as f64).sqrt() > error {
+            precision += 1;
+        }
+        HyperLogLog {
+            precision: precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    pub fn add(&mut self, value: &[u8]) {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(value);
+        let hash = hasher.finish();
+
+        // The first `precision` bits pick a register, which keeps the
+        // longest run of leading zeros seen in the rest of the bits.
+        let index = (hash >> (64 - self.precision)) as usize;
+        let rest = hash << self.precision;
+        let rank = cmp::min(rest.leading_zeros(), 64 - self.precision) + 1;
+        self.registers[index] = cmp::max(self.registers[index], rank as u8);
+    }
+
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self.registers.iter()
+            .map(|&r| 2f64.powi(-(r as i32)))
+            .sum();
+        let raw = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            raw.round() as u64
+        }
+    }
+}
+
+impl Commute for HyperLogLog {
+    fn merge(&mut self, other: HyperLogLog) {
+        assert_eq!(self.precision, other.precision);
+        for (r1, r2) in self.registers.iter_mut().zip(other.registers) {
+            *r1 = cmp::max(*r1, r2);
+        }
+    }
+}
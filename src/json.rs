@@ -0,0 +1,82 @@
+use std::fmt::Write;
+
+/// Object builds a JSON object, one member at a time.
+pub struct Object {
+    buf: String,
+}
+
+impl Object {
+    pub fn new() -> Object {
+        Object { buf: "{".to_owned() }
+    }
+
+    pub fn string(&mut self, key: &str, value: &str) -> &mut Object {
+        self.key(key);
+        push_string(&mut self.buf, value);
+        self
+    }
+
+    /// Add a number, which is written as is. If `value` isn't a finite
+    /// number, then it is written as `null` instead.
+    pub fn number(&mut self, key: &str, value: &str) -> &mut Object {
+        let valid = value.parse::<f64>().map(|n| n.is_finite())
+                         .unwrap_or(false);
+        self.raw(key, if valid { value } else { "null" })
+    }
+
+    pub fn null(&mut self, key: &str) -> &mut Object {
+        self.raw(key, "null")
+    }
+
+    /// Add a value that is already encoded as JSON.
+    pub fn raw(&mut self, key: &str, json: &str) -> &mut Object {
+        self.key(key);
+        self.buf.push_str(json);
+        self
+    }
+
+    pub fn finish(&self) -> String {
+        let mut json = self.buf.clone();
+        json.push('}');
+        json
+    }
+
+    fn key(&mut self, key: &str) {
+        if self.buf.len() > 1 {
+            self.buf.push(',');
+        }
+        push_string(&mut self.buf, key);
+        self.buf.push(':');
+    }
+}
+
+/// Encode a list of JSON values as an array, with one value per line.
+pub fn array(values: &[String]) -> String {
+    if values.is_empty() {
+        return "[]".to_owned();
+    }
+    format!("[\n  {}\n]", values.join(",\n  "))
+}
+
+/// Encode a list of JSON values as an array, on one line.
+pub fn inline_array(values: &[String]) -> String {
+    format!("[{}]", values.join(","))
+}
+
+fn push_string(buf: &mut String, s: &str) {
+    buf.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                write!(buf, "\\u{:04x}", c as u32).unwrap();
+            }
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+}
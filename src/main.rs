@@ -1,3 +1,4 @@
+extern crate aho_corasick;
 extern crate byteorder;
 extern crate crossbeam_channel as channel;
 extern crate csv;
@@ -45,17 +46,26 @@ macro_rules! command_list {
 "
     cat         Concatenate by row or column
     count       Count records
+    dedup       Remove duplicate rows
+    filter      Filter rows with an expression
     fixlengths  Makes all records have same length
     flatten     Show one field per line
     fmt         Format CSV output (change field delimiter)
     frequency   Show frequency tables
+    groupby     Compute aggregates for groups of rows
     headers     Show header names
     help        Show this usage message.
+    histogram   Show histograms of numeric columns
     index       Create CSV index for faster access
     input       Read CSV data with special quoting rules
     join        Join CSV files
+    map         Add or replace columns using expressions
+    melt        Reshape CSV data from wide to long format
     partition   Partition CSV data based on a column value
+    pivot       Reshape CSV data from long to wide format
     sample      Randomly sample CSV data
+    rename      Rename columns
+    replace     Replace patterns in CSV data
     reverse     Reverse rows of CSV data
     search      Search CSV data with regexes
     select      Select columns from CSV
@@ -64,14 +74,20 @@ macro_rules! command_list {
     split       Split CSV data into many files
     stats       Compute basic statistics
     table       Align CSV data into columns
+    transpose   Swap the rows and columns of CSV data
 "
     )
 }
 
 mod cmd;
 mod config;
+mod date;
+mod expr;
+mod hyperloglog;
 mod index;
+mod json;
 mod select;
+mod tdigest;
 mod util;
 
 static USAGE: &'static str = concat!("
@@ -142,16 +158,25 @@ Please choose one of the following commands:",
 enum Command {
     Cat,
     Count,
+    Dedup,
+    Filter,
     FixLengths,
     Flatten,
     Fmt,
     Frequency,
+    Groupby,
     Headers,
     Help,
+    Histogram,
     Index,
     Input,
     Join,
+    Map,
+    Melt,
     Partition,
+    Pivot,
+    Rename,
+    Replace,
     Reverse,
     Sample,
     Search,
@@ -161,6 +186,7 @@ enum Command {
     Split,
     Stats,
     Table,
+    Transpose,
 }
 
 impl Command {
@@ -177,16 +203,25 @@ impl Command {
         match self {
             Command::Cat => cmd::cat::run(argv),
             Command::Count => cmd::count::run(argv),
+            Command::Dedup => cmd::dedup::run(argv),
+            Command::Filter => cmd::filter::run(argv),
             Command::FixLengths => cmd::fixlengths::run(argv),
             Command::Flatten => cmd::flatten::run(argv),
             Command::Fmt => cmd::fmt::run(argv),
             Command::Frequency => cmd::frequency::run(argv),
+            Command::Groupby => cmd::groupby::run(argv),
             Command::Headers => cmd::headers::run(argv),
             Command::Help => { wout!("{}", USAGE); Ok(()) }
+            Command::Histogram => cmd::histogram::run(argv),
             Command::Index => cmd::index::run(argv),
             Command::Input => cmd::input::run(argv),
             Command::Join => cmd::join::run(argv),
+            Command::Map => cmd::map::run(argv),
+            Command::Melt => cmd::melt::run(argv),
             Command::Partition => cmd::partition::run(argv),
+            Command::Pivot => cmd::pivot::run(argv),
+            Command::Rename => cmd::rename::run(argv),
+            Command::Replace => cmd::replace::run(argv),
             Command::Reverse => cmd::reverse::run(argv),
             Command::Sample => cmd::sample::run(argv),
             Command::Search => cmd::search::run(argv),
@@ -196,6 +231,7 @@ impl Command {
             Command::Split => cmd::split::run(argv),
             Command::Stats => cmd::stats::run(argv),
             Command::Table => cmd::table::run(argv),
+            Command::Transpose => cmd::transpose::run(argv),
         }
     }
 }
@@ -0,0 +1,140 @@
+use std::cmp::Ordering;
+use std::f64;
+use std::f64::consts::PI;
+use std::mem;
+
+use stats::Commute;
+
+/// TDigest estimates quantiles of a stream of numbers in constant memory.
+///
+/// This is the merging t-digest described by Ted Dunning in "Computing
+/// Extremely Accurate Quantiles Using t-Digests". Samples are summarized by
+/// centroids (a mean and a weight), which are kept small near the tails so
+/// that extreme quantiles like the 99th percentile stay accurate. Two
+/// digests can be merged, which makes them suitable for computing quantiles
+/// in parallel.
+#[derive(Clone, Debug)]
+pub struct TDigest {
+    /// Bounds the number of centroids. Larger values are more accurate but
+    /// use more memory.
+    compression: f64,
+    /// Centroids, sorted by mean.
+    centroids: Vec<Centroid>,
+    /// Centroids that haven't been merged into `centroids` yet.
+    unmerged: Vec<Centroid>,
+    min: f64,
+    max: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+impl TDigest {
+    pub fn new(compression: f64) -> TDigest {
+        TDigest {
+            compression: compression,
+            centroids: vec![],
+            unmerged: vec![],
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn add(&mut self, sample: f64) {
+        self.min = self.min.min(sample);
+        self.max = self.max.max(sample);
+        self.unmerged.push(Centroid { mean: sample, weight: 1.0 });
+        if self.unmerged.len() >= 5 * self.compression as usize {
+            self.compress();
+        }
+    }
+
+    /// Estimate the `q`th quantile, where `q` is between `0` and `1`.
+    ///
+    /// Quantiles are interpolated linearly between the means of adjacent
+    /// centroids. While every centroid has a single sample, this gives the
+    /// same result as interpolating between the samples themselves.
+    pub fn quantile(&mut self, q: f64) -> Option<f64> {
+        self.compress();
+        if self.centroids.is_empty() {
+            return None;
+        }
+        let total: f64 = self.centroids.iter().map(|c| c.weight).sum();
+        let target = q * (total - 1.0) + 0.5;
+
+        // `prev` is the position and mean of the previous centroid's
+        // center, starting with the minimum.
+        let mut prev = (0.0, self.min);
+        let mut seen = 0.0;
+        for c in &self.centroids {
+            let center = (seen + c.weight / 2.0, c.mean);
+            if target <= center.0 {
+                return Some(interpolate(prev, center, target));
+            }
+            prev = center;
+            seen += c.weight;
+        }
+        Some(interpolate(prev, (total, self.max), target))
+    }
+
+    /// Merge all unmerged centroids, combining neighbouring centroids as
+    /// long as they stay within the size allowed by the scale function.
+    fn compress(&mut self) {
+        if self.unmerged.is_empty() {
+            return;
+        }
+        let mut all = mem::replace(&mut self.unmerged, vec![]);
+        all.append(&mut self.centroids);
+        all.sort_by(|a, b| {
+            a.mean.partial_cmp(&b.mean).unwrap_or(Ordering::Equal)
+        });
+
+        let total: f64 = all.iter().map(|c| c.weight).sum();
+        let mut seen = 0.0;
+        let mut limit = self.q_limit(0.0);
+        let mut cur = all[0];
+        for &c in &all[1..] {
+            if (seen + cur.weight + c.weight) / total <= limit {
+                cur.weight += c.weight;
+                cur.mean += (c.mean - cur.mean) * c.weight / cur.weight;
+            } else {
+                seen += cur.weight;
+                self.centroids.push(cur);
+                limit = self.q_limit(seen / total);
+                cur = c;
+            }
+        }
+        self.centroids.push(cur);
+    }
+
+    /// The largest quantile that a centroid starting at quantile `q` may
+    /// reach, using the scale function `k(q) = δ/2π · asin(2q - 1)`.
+    fn q_limit(&self, q: f64) -> f64 {
+        let k = self.compression / (2.0 * PI) * (2.0 * q - 1.0).asin();
+        let k = k + 1.0;
+        ((2.0 * PI * k / self.compression).min(PI / 2.0).sin() + 1.0) / 2.0
+    }
+}
+
+/// Interpolate linearly between the points `a` and `b` at position `x`.
+fn interpolate(a: (f64, f64), b: (f64, f64), x: f64) -> f64 {
+    if b.0 <= a.0 {
+        return b.1;
+    }
+    let t = ((x - a.0) / (b.0 - a.0)).max(0.0).min(1.0);
+    a.1 + (b.1 - a.1) * t
+}
+
+impl Commute for TDigest {
+    fn merge(&mut self, other: TDigest) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.unmerged.extend(other.centroids);
+        self.unmerged.extend(other.unmerged);
+        self.compress();
+    }
+}
+
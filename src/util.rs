@@ -119,6 +119,24 @@ pub fn idx_path(csv_path: &Path) -> PathBuf {
     PathBuf::from(&p)
 }
 
+/// Split `s` on `sep`, except where `sep` occurs inside double quotes.
+pub fn split_unquoted(s: &str, sep: char) -> Vec<&str> {
+    let mut pieces = vec![];
+    let (mut start, mut quoted) = (0, false);
+    for (i, c) in s.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == sep && !quoted {
+            pieces.push(&s[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    if start < s.len() {
+        pieces.push(&s[start..]);
+    }
+    pieces
+}
+
 pub type Idx = Option<usize>;
 
 pub fn range(start: Idx, end: Idx, len: Idx, index: Idx)
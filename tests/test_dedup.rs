@@ -0,0 +1,157 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["k", "v"],
+        svec!["a", "1"],
+        svec!["b", "2"],
+        svec!["a", "3"],
+        svec!["c", "4"],
+        svec!["b", "5"],
+        svec!["a", "1"],
+    ]
+}
+
+fn sorted_data() -> Vec<Vec<String>> {
+    vec![
+        svec!["k", "v"],
+        svec!["a", "1"],
+        svec!["a", "3"],
+        svec!["a", "1"],
+        svec!["b", "2"],
+        svec!["b", "5"],
+        svec!["c", "4"],
+    ]
+}
+
+#[test]
+fn dedup_all_columns() {
+    let wrk = Workdir::new("dedup_all_columns");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("dedup");
+    cmd.arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["a", "1"],
+        svec!["b", "2"],
+        svec!["a", "3"],
+        svec!["c", "4"],
+        svec!["b", "5"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_select_first() {
+    let wrk = Workdir::new("dedup_select_first");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["--select", "k"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["a", "1"],
+        svec!["b", "2"],
+        svec!["c", "4"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_select_last() {
+    let wrk = Workdir::new("dedup_select_last");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["--select", "k"]).arg("--keep-last").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["c", "4"],
+        svec!["b", "5"],
+        svec!["a", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_sorted_first() {
+    let wrk = Workdir::new("dedup_sorted_first");
+    wrk.create("in.csv", sorted_data());
+
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["--select", "k"]).arg("--sorted").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["a", "1"],
+        svec!["b", "2"],
+        svec!["c", "4"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_sorted_last() {
+    let wrk = Workdir::new("dedup_sorted_last");
+    wrk.create("in.csv", sorted_data());
+
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["--select", "k"]).arg("--sorted").arg("--keep-last")
+       .arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["a", "1"],
+        svec!["b", "5"],
+        svec!["c", "4"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_dupes_output() {
+    let wrk = Workdir::new("dedup_dupes_output");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("dedup");
+    cmd.args(&["--select", "k"]).args(&["--dupes-output", "dupes.csv"])
+       .arg("in.csv");
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("cat");
+    cmd.arg("rows").arg("dupes.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["a", "3"],
+        svec!["b", "5"],
+        svec!["a", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_no_headers() {
+    let wrk = Workdir::new("dedup_no_headers");
+    wrk.create("in.csv", vec![
+        svec!["a", "1"],
+        svec!["a", "1"],
+        svec!["b", "2"],
+    ]);
+
+    let mut cmd = wrk.command("dedup");
+    cmd.arg("--no-headers").args(&["--select", "1"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![svec!["a", "1"], svec!["b", "2"]];
+    assert_eq!(got, expected);
+}
@@ -0,0 +1,175 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "amount", "status", "Unit Price", "name"],
+        svec!["1", "150", "ok", "2.5", "Alice"],
+        svec!["2", "50", "ok", "3", "bob"],
+        svec!["3", "200", "void", "", "Carol"],
+        svec!["4", "9", "ok", "1e3", ""],
+        svec!["5", "10", "void", "4", "dave"],
+    ]
+}
+
+fn ids(wrk: &Workdir, args: &[&str]) -> Vec<String> {
+    let mut cmd = wrk.command("filter");
+    cmd.args(args).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    got.into_iter().skip(1).map(|row| row[0].clone()).collect()
+}
+
+#[test]
+fn filter_numeric_and_string() {
+    let wrk = Workdir::new("filter_numeric_and_string");
+    wrk.create("in.csv", data());
+
+    let got = ids(&wrk, &["amount > 100 && status != \"void\""]);
+    assert_eq!(got, svec!["1"]);
+}
+
+#[test]
+fn filter_numeric_not_lexicographic() {
+    let wrk = Workdir::new("filter_numeric_not_lexicographic");
+    wrk.create("in.csv", data());
+
+    // Lexicographically, "9" > "10" but numerically it isn't.
+    let got = ids(&wrk, &["amount >= 10"]);
+    assert_eq!(got, svec!["1", "2", "3", "5"]);
+}
+
+#[test]
+fn filter_keeps_headers() {
+    let wrk = Workdir::new("filter_keeps_headers");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("filter");
+    cmd.arg("id == 2").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "amount", "status", "Unit Price", "name"],
+        svec!["2", "50", "ok", "3", "bob"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn filter_quoted_column_and_arith() {
+    let wrk = Workdir::new("filter_quoted_column_and_arith");
+    wrk.create("in.csv", data());
+
+    let got = ids(&wrk, &["`Unit Price` * 2 >= 6"]);
+    assert_eq!(got, svec!["2", "4", "5"]);
+}
+
+#[test]
+fn filter_integer_division_overflow() {
+    let wrk = Workdir::new("filter_integer_division_overflow");
+    wrk.create("in.csv", vec![
+        svec!["id", "a", "b"],
+        svec!["1", "-9223372036854775808", "-1"],
+        svec!["2", "6", "-3"],
+    ]);
+
+    // i64::MIN / -1 overflows as an integer, so it's done with floats.
+    let got = ids(&wrk, &["a / b > 0"]);
+    assert_eq!(got, svec!["1"]);
+}
+
+#[test]
+fn filter_column_index() {
+    let wrk = Workdir::new("filter_column_index");
+    wrk.create("in.csv", data());
+
+    let got = ids(&wrk, &["#2 % 2 == 0 && #3 == 'ok'"]);
+    assert_eq!(got, svec!["1", "2"]);
+}
+
+#[test]
+fn filter_backticked_names() {
+    let wrk = Workdir::new("filter_backticked_names");
+    wrk.create("in.csv", vec![
+        svec!["id", "order-id", "2019", "x", "x"],
+        svec!["1", "7", "a", "p", "q"],
+        svec!["2", "8", "b", "r", "s"],
+    ]);
+
+    assert_eq!(ids(&wrk, &["`order-id` == 8"]), svec!["2"]);
+    assert_eq!(ids(&wrk, &["`2019` == 'a'"]), svec!["1"]);
+    assert_eq!(ids(&wrk, &["`x`[1] == 's'"]), svec!["2"]);
+    assert_eq!(ids(&wrk, &["`x` == 'p'"]), svec!["1"]);
+}
+
+#[test]
+fn filter_nulls() {
+    let wrk = Workdir::new("filter_nulls");
+    wrk.create("in.csv", data());
+
+    assert_eq!(ids(&wrk, &["name == null"]), svec!["4"]);
+    assert_eq!(ids(&wrk, &["is_null(`Unit Price`)"]), svec!["3"]);
+    // Nulls are never less than or greater than anything.
+    assert_eq!(ids(&wrk, &["`Unit Price` < 3"]), svec!["1"]);
+}
+
+#[test]
+fn filter_string_functions() {
+    let wrk = Workdir::new("filter_string_functions");
+    wrk.create("in.csv", data());
+
+    assert_eq!(ids(&wrk, &["lower(name) =~ '^[ab]'"]), svec!["1", "2"]);
+    assert_eq!(ids(&wrk, &["name !~ 'a'"]), svec!["1", "2", "4"]);
+    assert_eq!(ids(&wrk, &["starts_with(upper(name), 'CA')"]), svec!["3"]);
+    assert_eq!(ids(&wrk, &["contains(name, 'ob') || len(name) == 4"]),
+               svec!["2", "5"]);
+    assert_eq!(ids(&wrk, &["matches(status, 'oi')"]), svec!["3", "5"]);
+}
+
+#[test]
+fn filter_numeric_field_text() {
+    let wrk = Workdir::new("filter_numeric_field_text");
+    wrk.create("in.csv", vec![
+        svec!["id", "zip", "big"],
+        svec!["1", "02134", "12345678901234567890"],
+        svec!["2", "2134", "1"],
+    ]);
+
+    assert_eq!(ids(&wrk, &["len(zip) == 5"]), svec!["1"]);
+    assert_eq!(ids(&wrk, &["starts_with(zip, '0')"]), svec!["1"]);
+    assert_eq!(ids(&wrk, &["zip =~ '^0'"]), svec!["1"]);
+    assert_eq!(ids(&wrk, &["zip == 2134"]), svec!["1", "2"]);
+    assert_eq!(ids(&wrk, &["concat(big, '') == '12345678901234567890'"]),
+               svec!["1"]);
+}
+
+#[test]
+fn filter_invert() {
+    let wrk = Workdir::new("filter_invert");
+    wrk.create("in.csv", data());
+
+    let got = ids(&wrk, &["-v", "status == 'ok'"]);
+    assert_eq!(got, svec!["3", "5"]);
+}
+
+#[test]
+fn filter_no_headers() {
+    let wrk = Workdir::new("filter_no_headers");
+    wrk.create("in.csv", vec![svec!["a", "1"], svec!["b", "2"]]);
+
+    let mut cmd = wrk.command("filter");
+    cmd.arg("--no-headers").arg("#2 > 1").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["b", "2"]]);
+}
+
+#[test]
+fn filter_errors() {
+    let wrk = Workdir::new("filter_errors");
+    wrk.create("in.csv", data());
+
+    for expr in &["amount >", "nope == 1", "foo(1)", "'unclosed", "(1",
+                  "name =~ name", "len(1, 2)",
+                  "`name`[x] == 1", "`name`[1] == 1"] {
+        let mut cmd = wrk.command("filter");
+        cmd.arg(expr).arg("in.csv");
+        wrk.assert_err(&mut cmd);
+    }
+}
@@ -260,3 +260,127 @@ fn assert_eq_ftables(got: &FTables, expected: &FTables) -> bool {
     }
     true
 }
+
+#[test]
+fn frequency_cardinality() {
+    let (wrk, mut cmd) = setup("frequency_cardinality");
+    cmd.arg("--cardinality");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "cardinality"],
+        svec!["h1", "4"],
+        svec!["h2", "3"],
+    ];
+    assert_eq!(got, expected);
+
+    let (wrk, mut cmd) = setup("frequency_cardinality_approx");
+    cmd.args(&["--cardinality", "--approx", "--no-nulls"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "cardinality"],
+        svec!["h1", "3"],
+        svec!["h2", "3"],
+    ];
+    assert_eq!(got, expected);
+
+    let (wrk, mut cmd) = setup("frequency_approx_only");
+    cmd.arg("--approx");
+    wrk.assert_err(&mut cmd);
+
+    let (wrk, mut cmd) = setup("frequency_approx_small_error_rate");
+    cmd.args(&["--cardinality", "--approx", "--error-rate", "0.001"]);
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn frequency_cardinality_approx_indexed() {
+    let wrk = Workdir::new("frequency_cardinality_approx_indexed");
+    let mut rows = vec![svec!["id"]];
+    for i in 0..20000 {
+        rows.push(vec![(i / 4).to_string()]);
+    }
+    wrk.create_indexed("in.csv", rows);
+
+    let mut cmd = wrk.command("frequency");
+    cmd.args(&["--cardinality", "--approx", "-j", "4"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let ids: f64 = got[1][1].parse().unwrap();
+    assert!((ids - 5000.0).abs() < 150.0, "{} is too far from 5000", ids);
+}
+
+#[test]
+fn frequency_json() {
+    let wrk = Workdir::new("frequency_json");
+    wrk.create("in.csv", vec![
+        svec!["h1", "h2"],
+        svec!["a", "x"],
+        svec!["", "\"y\""],
+        svec!["a", "x"],
+    ]);
+
+    let mut cmd = wrk.command("frequency");
+    cmd.arg("--json").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+[
+  {\"field\":\"h1\",\"values\":[{\"value\":\"a\",\"count\":2},\
+{\"value\":null,\"count\":1}]},
+  {\"field\":\"h2\",\"values\":[{\"value\":\"x\",\"count\":2},\
+{\"value\":\"\\\"y\\\"\",\"count\":1}]}
+]";
+    assert_eq!(got, expected);
+
+    let mut cmd = wrk.command("frequency");
+    cmd.args(&["--json", "--cardinality", "--no-headers"]).arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+[
+  {\"field\":\"1\",\"cardinality\":3},
+  {\"field\":\"2\",\"cardinality\":3}
+]";
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_percent() {
+    let (wrk, mut cmd) = setup("frequency_percent");
+    cmd.args(&["--select", "h2", "--percent"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "value", "count", "percent", "cumulative_percent"],
+        svec!["h2", "z", "3", "50", "50"],
+        svec!["h2", "y", "2", "33.333333333333336", "83.33333333333333"],
+        svec!["h2", "x", "1", "16.666666666666668", "100"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn frequency_other() {
+    let (wrk, mut cmd) = setup("frequency_other");
+    cmd.args(&["--limit", "1", "--other", "--percent"]);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "value", "count", "percent", "cumulative_percent"],
+        svec!["h1", "a", "3", "50", "50"],
+        svec!["h1", "(other)", "3", "50", "100"],
+        svec!["h2", "z", "3", "50", "50"],
+        svec!["h2", "(other)", "3", "50", "100"],
+    ];
+    assert_eq!(got, expected);
+
+    // Without a limit, nothing is left out, so there's no '(other)' row.
+    let (wrk, mut cmd) = setup("frequency_other_no_limit");
+    cmd.args(&["--limit", "0", "--other", "--select", "h2"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "value", "count"],
+        svec!["h2", "z", "3"],
+        svec!["h2", "y", "2"],
+        svec!["h2", "x", "1"],
+    ];
+    assert_eq!(got, expected);
+}
@@ -0,0 +1,117 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["region", "name", "price", "qty"],
+        svec!["east", "a", "1", "10"],
+        svec!["west", "b", "2.5", "20"],
+        svec!["east", "c", "3", ""],
+        svec!["north", "d", "", "5"],
+        svec!["east", "a", "5", "30"],
+        svec!["west", "e", "x", "40"],
+    ]
+}
+
+fn groupby(wrk: &Workdir, group: &str, agg: &str) -> Vec<Vec<String>> {
+    let mut cmd = wrk.command("groupby");
+    cmd.args(&["--agg", agg]).arg(group).arg("in.csv");
+    wrk.read_stdout(&mut cmd)
+}
+
+#[test]
+fn groupby_count() {
+    let wrk = Workdir::new("groupby_count");
+    wrk.create("in.csv", data());
+
+    let got = groupby(&wrk, "region", "count,count:qty,count-distinct:name");
+    let expected = vec![
+        svec!["region", "count", "qty_count", "name_count-distinct"],
+        svec!["east", "3", "2", "2"],
+        svec!["west", "2", "2", "2"],
+        svec!["north", "1", "1", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn groupby_numbers() {
+    let wrk = Workdir::new("groupby_numbers");
+    wrk.create("in.csv", data());
+
+    let got = groupby(&wrk, "region", "sum:price-qty,mean:qty,min:3,max:3");
+    let expected = vec![
+        svec!["region", "price_sum", "qty_sum", "qty_mean",
+              "price_min", "price_max"],
+        svec!["east", "9", "40", "20", "1", "5"],
+        svec!["west", "", "60", "30", "2.5", "x"],
+        svec!["north", "", "5", "5", "", ""],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn groupby_first_last() {
+    let wrk = Workdir::new("groupby_first_last");
+    wrk.create("in.csv", data());
+
+    let got = groupby(&wrk, "region,name", "first:qty,last:qty");
+    let expected = vec![
+        svec!["region", "name", "qty_first", "qty_last"],
+        svec!["east", "a", "10", "30"],
+        svec!["west", "b", "20", "20"],
+        svec!["east", "c", "", ""],
+        svec!["north", "d", "5", "5"],
+        svec!["west", "e", "40", "40"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn groupby_index() {
+    let wrk = Workdir::new("groupby_index");
+    let mut rows = vec![svec!["group", "n"]];
+    for i in 0..100 {
+        rows.push(vec![(i % 3).to_string(), i.to_string()]);
+    }
+    wrk.create_indexed("in.csv", rows);
+
+    let mut cmd = wrk.command("groupby");
+    cmd.args(&["--agg", "count,sum:n,first:n,last:n", "--jobs", "4"]);
+    cmd.arg("group").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["group", "count", "n_sum", "n_first", "n_last"],
+        svec!["0", "34", "1683", "0", "99"],
+        svec!["1", "33", "1617", "1", "97"],
+        svec!["2", "33", "1650", "2", "98"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn groupby_no_headers() {
+    let wrk = Workdir::new("groupby_no_headers");
+    wrk.create("in.csv", vec![svec!["a", "1"], svec!["a", "2"]]);
+
+    let mut cmd = wrk.command("groupby");
+    cmd.args(&["--agg", "sum:2", "--no-headers"]).arg("1").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["1", "2_sum"],
+        svec!["a", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn groupby_bad_agg() {
+    let wrk = Workdir::new("groupby_bad_agg");
+    wrk.create("in.csv", data());
+
+    for agg in &["median:qty", "sum", "sum:nope", "sum:qty:x",
+                 "count,,sum:qty", " ,count", ""] {
+        let mut cmd = wrk.command("groupby");
+        cmd.args(&["--agg", agg]).arg("region").arg("in.csv");
+        wrk.assert_err(&mut cmd);
+    }
+}
@@ -0,0 +1,137 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["n", "x", "name"],
+        svec!["1", "0.5", "a"],
+        svec!["2", "", "b"],
+        svec!["3", "1.5", "c"],
+        svec!["4", "2", "d"],
+        svec!["10", "7", "e"],
+    ]
+}
+
+fn histogram(wrk: &Workdir, args: &[&str]) -> Vec<Vec<String>> {
+    let mut cmd = wrk.command("histogram");
+    cmd.args(args).arg("in.csv");
+    wrk.read_stdout(&mut cmd)
+}
+
+#[test]
+fn histogram_equal_width() {
+    let wrk = Workdir::new("histogram_equal_width");
+    wrk.create("in.csv", data());
+
+    let got = histogram(&wrk, &["--bins", "3", "--select", "n"]);
+    let expected = vec![
+        svec!["field", "lower", "upper", "count"],
+        svec!["n", "1", "4", "3"],
+        svec!["n", "4", "7", "1"],
+        svec!["n", "7", "10", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn histogram_skips_non_numeric() {
+    let wrk = Workdir::new("histogram_skips_non_numeric");
+    wrk.create("in.csv", data());
+
+    let got = histogram(&wrk, &["--bins", "2"]);
+    let expected = vec![
+        svec!["field", "lower", "upper", "count"],
+        svec!["n", "1", "5.5", "4"],
+        svec!["n", "5.5", "10", "1"],
+        svec!["x", "0.5", "3.75", "3"],
+        svec!["x", "3.75", "7", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn histogram_quantiles() {
+    let wrk = Workdir::new("histogram_quantiles");
+    wrk.create("in.csv", data());
+
+    let got = histogram(&wrk, &["--quantiles", "--bins", "2"]);
+    let expected = vec![
+        svec!["field", "lower", "upper", "count"],
+        svec!["n", "1", "3", "2"],
+        svec!["n", "3", "10", "3"],
+        svec!["x", "0.5", "1.75", "2"],
+        svec!["x", "1.75", "7", "2"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn histogram_same_values() {
+    let wrk = Workdir::new("histogram_same_values");
+    wrk.create("in.csv", vec![svec!["n"], svec!["2"], svec!["2"]]);
+
+    let expected = vec![
+        svec!["field", "lower", "upper", "count"],
+        svec!["n", "2", "2", "2"],
+    ];
+    assert_eq!(histogram(&wrk, &[]), expected);
+    assert_eq!(histogram(&wrk, &["--quantiles"]), expected);
+}
+
+#[test]
+fn histogram_edges() {
+    let wrk = Workdir::new("histogram_edges");
+    wrk.create("in.csv", data());
+
+    let got = histogram(&wrk, &["--edges", "0,2,4"]);
+    let expected = vec![
+        svec!["field", "lower", "upper", "count"],
+        svec!["n", "0", "2", "1"],
+        svec!["n", "2", "4", "3"],
+        svec!["x", "0", "2", "2"],
+        svec!["x", "2", "4", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn histogram_no_headers() {
+    let wrk = Workdir::new("histogram_no_headers");
+    wrk.create("in.csv", vec![svec!["a", "1"], svec!["b", "3"]]);
+
+    let got = histogram(&wrk, &["--bins", "1", "--no-headers"]);
+    let expected = vec![
+        svec!["field", "lower", "upper", "count"],
+        svec!["2", "1", "3", "2"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn histogram_non_finite() {
+    let wrk = Workdir::new("histogram_non_finite");
+    wrk.create("in.csv", vec![
+        svec!["n"], svec!["NaN"], svec!["1"], svec!["inf"], svec!["3"],
+    ]);
+
+    let got = histogram(&wrk, &["--bins", "2"]);
+    let expected = vec![
+        svec!["field", "lower", "upper", "count"],
+        svec!["n", "1", "2", "1"],
+        svec!["n", "2", "3", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn histogram_invalid_edges() {
+    let wrk = Workdir::new("histogram_invalid_edges");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("histogram");
+    cmd.args(&["--edges", "2,1"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("histogram");
+    cmd.args(&["--edges", "1,2", "--quantiles"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
@@ -0,0 +1,138 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "qty", "price", "date", "note"],
+        svec!["1", "2", "1.5", "2018-03-01", "fast ship"],
+        svec!["2", "3", "4", "2019-11-20", ""],
+        svec!["3", "", "2", "2020-01-05", "slow ship"],
+    ]
+}
+
+fn map(wrk: &Workdir, expr: &str) -> Vec<Vec<String>> {
+    let mut cmd = wrk.command("map");
+    cmd.arg(expr).arg("in.csv");
+    wrk.read_stdout(&mut cmd)
+}
+
+/// Return a single column (by index) of the output, without the header.
+fn column(rows: Vec<Vec<String>>, i: usize) -> Vec<String> {
+    rows.into_iter().skip(1).map(|row| row[i].clone()).collect()
+}
+
+#[test]
+fn map_append_column() {
+    let wrk = Workdir::new("map_append_column");
+    wrk.create("in.csv", data());
+
+    let got = map(&wrk, "total = qty * price");
+    assert_eq!(got[0], svec!["id", "qty", "price", "date", "note", "total"]);
+    assert_eq!(column(got, 5), svec!["3", "12", ""]);
+}
+
+#[test]
+fn map_replace_column() {
+    let wrk = Workdir::new("map_replace_column");
+    wrk.create("in.csv", data());
+
+    let got = map(&wrk, "date = substr(date, 0, 4)");
+    assert_eq!(got[0], svec!["id", "qty", "price", "date", "note"]);
+    assert_eq!(column(got, 3), svec!["2018", "2019", "2020"]);
+}
+
+#[test]
+fn map_replace_column_by_index() {
+    let wrk = Workdir::new("map_replace_column_by_index");
+    wrk.create("in.csv", data());
+
+    let got = map(&wrk, "#5 = replace(note, 'ship', 'delivery')");
+    assert_eq!(column(got, 4),
+               svec!["fast delivery", "", "slow delivery"]);
+}
+
+#[test]
+fn map_replace_backticked_column() {
+    let wrk = Workdir::new("map_replace_backticked_column");
+    wrk.create("in.csv", vec![svec!["order-id", "2019"], svec!["7", "a"]]);
+
+    let got = map(&wrk, "`order-id` = `order-id` + 1; `2019` = upper(`2019`)");
+    assert_eq!(got, vec![svec!["order-id", "2019"], svec!["8", "A"]]);
+}
+
+#[test]
+fn map_chained_definitions() {
+    let wrk = Workdir::new("map_chained_definitions");
+    wrk.create("in.csv", data());
+
+    let got = map(&wrk, "qty = coalesce(qty, 0); total = qty * price; \
+                         big = if(total > 5, 'yes', 'no')");
+    assert_eq!(got[0],
+               svec!["id", "qty", "price", "date", "note", "total", "big"]);
+    assert_eq!(column(got.clone(), 1), svec!["2", "3", "0"]);
+    assert_eq!(column(got.clone(), 5), svec!["3", "12", "0"]);
+    assert_eq!(column(got, 6), svec!["no", "yes", "no"]);
+}
+
+#[test]
+fn map_numeric_functions() {
+    let wrk = Workdir::new("map_numeric_functions");
+    wrk.create("in.csv", data());
+
+    let got = map(&wrk, "a = round(price / 3, 2); b = floor(price / 3); \
+                         c = ceil(price / 3); d = abs(0 - price)");
+    assert_eq!(got[1][5..], svec!["0.5", "0", "1", "1.5"][..]);
+    assert_eq!(got[2][5..], svec!["1.33", "1", "2", "4"][..]);
+}
+
+#[test]
+fn map_concat() {
+    let wrk = Workdir::new("map_concat");
+    wrk.create("in.csv", data());
+
+    let got = map(&wrk, "label = concat(id, ':', upper(note))");
+    assert_eq!(column(got, 5), svec!["1:FAST SHIP", "2:", "3:SLOW SHIP"]);
+}
+
+#[test]
+fn map_copies_field_text() {
+    let wrk = Workdir::new("map_copies_field_text");
+    wrk.create("in.csv", vec![
+        svec!["a", "b"],
+        svec!["x", "1.50"],
+        svec!["y", "1e3"],
+        svec!["z", "007"],
+    ]);
+
+    let got = map(&wrk, "c = b; d = concat(a, b); e = if(a == 'z', b)");
+    assert_eq!(column(got.clone(), 2), svec!["1.50", "1e3", "007"]);
+    assert_eq!(column(got.clone(), 3), svec!["x1.50", "y1e3", "z007"]);
+    assert_eq!(column(got, 4), svec!["", "", "007"]);
+
+    let got = map(&wrk, "b = b + 0");
+    assert_eq!(column(got, 1), svec!["1.5", "1000", "7"]);
+}
+
+#[test]
+fn map_no_headers() {
+    let wrk = Workdir::new("map_no_headers");
+    wrk.create("in.csv", vec![svec!["1", "2"], svec!["3", "4"]]);
+
+    let mut cmd = wrk.command("map");
+    cmd.arg("#1 = #1 + #2; sum = #1 * 10").arg("--no-headers").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![svec!["3", "2", "30"], svec!["7", "4", "70"]];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn map_errors() {
+    let wrk = Workdir::new("map_errors");
+    wrk.create("in.csv", data());
+
+    for expr in &["total", "total = ", "#9 = 1", "x = nope + 1",
+                  "x = substr(note)"] {
+        let mut cmd = wrk.command("map");
+        cmd.arg(expr).arg("in.csv");
+        wrk.assert_err(&mut cmd);
+    }
+}
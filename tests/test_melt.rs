@@ -0,0 +1,92 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["name", "city", "2017", "2018"],
+        svec!["alice", "Boston", "1", "2"],
+        svec!["bob", "Austin", "3", ""],
+    ]
+}
+
+#[test]
+fn melt_id() {
+    let wrk = Workdir::new("melt_id");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("melt");
+    cmd.args(&["--id", "name,city"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "city", "variable", "value"],
+        svec!["alice", "Boston", "2017", "1"],
+        svec!["alice", "Boston", "2018", "2"],
+        svec!["bob", "Austin", "2017", "3"],
+        svec!["bob", "Austin", "2018", ""],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn melt_values_and_names() {
+    let wrk = Workdir::new("melt_values_and_names");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("melt");
+    cmd.args(&["--id", "name", "--values", "3-4"]);
+    cmd.args(&["--variable-name", "year", "--value-name", "sales"]);
+    cmd.arg("--drop-empty").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "year", "sales"],
+        svec!["alice", "2017", "1"],
+        svec!["alice", "2018", "2"],
+        svec!["bob", "2017", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn melt_no_id() {
+    let wrk = Workdir::new("melt_no_id");
+    wrk.create("in.csv", vec![svec!["a", "b"], svec!["1", "2"]]);
+    let mut cmd = wrk.command("melt");
+    cmd.arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["variable", "value"],
+        svec!["a", "1"],
+        svec!["b", "2"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn melt_no_headers() {
+    let wrk = Workdir::new("melt_no_headers");
+    wrk.create("in.csv", vec![svec!["x", "1", "2"]]);
+    let mut cmd = wrk.command("melt");
+    cmd.args(&["--id", "1"]).arg("--no-headers").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["x", "2", "1"],
+        svec!["x", "3", "2"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn melt_pivot_roundtrip() {
+    let wrk = Workdir::new("melt_pivot_roundtrip");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("melt");
+    cmd.args(&["--id", "name,city"]).args(&["-o", "long.csv"]);
+    cmd.arg("in.csv");
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("pivot");
+    cmd.arg("variable").arg("value").arg("long.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, data());
+}
@@ -0,0 +1,85 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["name", "year", "sales"],
+        svec!["alice", "2017", "1"],
+        svec!["alice", "2018", "2"],
+        svec!["bob", "2017", "3"],
+        svec!["alice", "2017", "4.5"],
+        svec!["carol", "2019", "n/a"],
+    ]
+}
+
+fn pivot(wrk: &Workdir, agg: &str) -> Vec<Vec<String>> {
+    let mut cmd = wrk.command("pivot");
+    cmd.arg("year").arg("sales").args(&["--agg", agg]).arg("in.csv");
+    wrk.read_stdout(&mut cmd)
+}
+
+#[test]
+fn pivot_first() {
+    let wrk = Workdir::new("pivot_first");
+    wrk.create("in.csv", data());
+
+    let expected = vec![
+        svec!["name", "2017", "2018", "2019"],
+        svec!["alice", "1", "2", ""],
+        svec!["bob", "3", "", ""],
+        svec!["carol", "", "", "n/a"],
+    ];
+    assert_eq!(pivot(&wrk, "first"), expected);
+}
+
+#[test]
+fn pivot_aggregations() {
+    let wrk = Workdir::new("pivot_aggregations");
+    wrk.create("in.csv", data());
+
+    let cell = |agg: &str| pivot(&wrk, agg)[1][1].clone();
+    assert_eq!(cell("last"), "4.5");
+    assert_eq!(cell("count"), "2");
+    assert_eq!(cell("sum"), "5.5");
+    assert_eq!(cell("mean"), "2.75");
+    assert_eq!(cell("min"), "1");
+    assert_eq!(cell("max"), "4.5");
+
+    // Non-numeric values are ignored by numeric aggregations.
+    assert_eq!(pivot(&wrk, "sum")[3][3], "");
+    assert_eq!(pivot(&wrk, "count")[3][3], "1");
+}
+
+#[test]
+fn pivot_id_columns() {
+    let wrk = Workdir::new("pivot_id_columns");
+    wrk.create("in.csv", vec![
+        svec!["region", "name", "year", "sales"],
+        svec!["east", "alice", "2017", "1"],
+        svec!["east", "bob", "2017", "2"],
+        svec!["west", "carol", "2018", "3"],
+    ]);
+    let mut cmd = wrk.command("pivot");
+    cmd.args(&["--id", "region", "--agg", "sum"]);
+    cmd.arg("year").arg("sales").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["region", "2017", "2018"],
+        svec!["east", "3", ""],
+        svec!["west", "", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn pivot_bad_column() {
+    let wrk = Workdir::new("pivot_bad_column");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("pivot");
+    cmd.arg("name,year").arg("sales").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("pivot");
+    cmd.arg("year").arg("sales").args(&["--agg", "median"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
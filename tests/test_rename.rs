@@ -0,0 +1,99 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["ID", " First Name ", "lastName", "col_x"],
+        svec!["1", "Ada", "Lovelace", "a"],
+        svec!["2", "Alan", "Turing", "b"],
+    ]
+}
+
+fn headers(wrk: &Workdir, args: &[&str]) -> Vec<String> {
+    let mut cmd = wrk.command("rename");
+    cmd.args(args).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1..], data()[1..]);
+    got[0].clone()
+}
+
+#[test]
+fn rename_all() {
+    let wrk = Workdir::new("rename_all");
+    wrk.create("in.csv", data());
+
+    let got = headers(&wrk, &["id,first,\"last, name\",x"]);
+    assert_eq!(got, svec!["id", "first", "last, name", "x"]);
+}
+
+#[test]
+fn rename_select() {
+    let wrk = Workdir::new("rename_select");
+    wrk.create("in.csv", data());
+
+    let got = headers(&wrk, &["-s", "2-3", "first,last"]);
+    assert_eq!(got, svec!["ID", "first", "last", "col_x"]);
+}
+
+#[test]
+fn rename_wrong_count() {
+    let wrk = Workdir::new("rename_wrong_count");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("rename");
+    cmd.arg("a,b").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn rename_pairs() {
+    let wrk = Workdir::new("rename_pairs");
+    wrk.create("in.csv", data());
+
+    let got = headers(&wrk, &["--pairs", "ID:id,lastName:\"last:name\",4:x"]);
+    assert_eq!(got, svec!["id", " First Name ", "last:name", "x"]);
+
+    let mut cmd = wrk.command("rename");
+    cmd.arg("--pairs").arg("nope:x").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn rename_regex() {
+    let wrk = Workdir::new("rename_regex");
+    wrk.create("in.csv", data());
+
+    let got = headers(&wrk, &["--regex", "^col_(.*)$", "${1}_col"]);
+    assert_eq!(got, svec!["ID", " First Name ", "lastName", "x_col"]);
+}
+
+#[test]
+fn rename_normalize() {
+    let wrk = Workdir::new("rename_normalize");
+    wrk.create("in.csv", data());
+
+    let got = headers(&wrk, &["--normalize", "snake"]);
+    assert_eq!(got, svec!["id", "first_name", "last_name", "col_x"]);
+
+    let got = headers(&wrk, &["--normalize", "trim,lower"]);
+    assert_eq!(got, svec!["id", "first name", "lastname", "col_x"]);
+
+    let got = headers(&wrk, &["--normalize", "lower", "-s", "1", "--regex",
+                              "I", "X"]);
+    assert_eq!(got, svec!["xd", " First Name ", "lastName", "col_x"]);
+}
+
+#[test]
+fn rename_no_headers() {
+    let wrk = Workdir::new("rename_no_headers");
+    wrk.create("in.csv", vec![svec!["1", "a"], svec!["2", "b"]]);
+
+    let mut cmd = wrk.command("rename");
+    cmd.arg("--no-headers").arg("n,letter").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["n", "letter"],
+        svec!["1", "a"],
+        svec!["2", "b"],
+    ];
+    assert_eq!(got, expected);
+}
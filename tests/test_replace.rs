@@ -0,0 +1,97 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["name", "date", "note"],
+        svec!["alice", "03/01/2018", "a-b-c"],
+        svec!["bob", "11/20/2019", "none"],
+        svec!["carol", "2020-01-05", "x-y"],
+    ]
+}
+
+#[test]
+fn replace_capture_groups() {
+    let wrk = Workdir::new("replace_capture_groups");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("replace");
+    cmd.arg(r"(\d\d)/(\d\d)/(\d{4})").arg("$3-$1-$2").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "date", "note"],
+        svec!["alice", "2018-03-01", "a-b-c"],
+        svec!["bob", "2019-11-20", "none"],
+        svec!["carol", "2020-01-05", "x-y"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn replace_select_and_first() {
+    let wrk = Workdir::new("replace_select_and_first");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("replace");
+    cmd.arg("-").arg("_").args(&["--select", "note"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "date", "note"],
+        svec!["alice", "03/01/2018", "a_b_c"],
+        svec!["bob", "11/20/2019", "none"],
+        svec!["carol", "2020-01-05", "x_y"],
+    ];
+    assert_eq!(got, expected);
+
+    let mut cmd = wrk.command("replace");
+    cmd.arg("-").arg("_").args(&["--select", "note"]).arg("--first");
+    cmd.arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1], svec!["alice", "03/01/2018", "a_b-c"]);
+}
+
+#[test]
+fn replace_ignore_case_named_group() {
+    let wrk = Workdir::new("replace_ignore_case_named_group");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("replace");
+    cmd.arg("^(?P<first>[A-C])").arg("<$first>").arg("-i").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "date", "note"],
+        svec!["<a>lice", "03/01/2018", "<a>-b-c"],
+        svec!["<b>ob", "11/20/2019", "none"],
+        svec!["<c>arol", "2020-01-05", "x-y"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn replace_no_headers() {
+    let wrk = Workdir::new("replace_no_headers");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("replace");
+    cmd.arg("e").arg("E").args(&["--select", "1"]).arg("--no-headers");
+    cmd.arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec!["namE", "date", "note"]);
+    assert_eq!(got[1], svec!["alicE", "03/01/2018", "a-b-c"]);
+}
+
+#[test]
+fn replace_reports_count() {
+    let wrk = Workdir::new("replace_reports_count");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("replace");
+    cmd.arg("-").arg("").arg("in.csv");
+
+    let output = wrk.output(&mut cmd);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.trim(), "5 substitution(s) made.");
+
+    let mut cmd = wrk.command("replace");
+    cmd.arg("-").arg("").arg("--quiet").arg("in.csv");
+    let output = wrk.output(&mut cmd);
+    assert!(output.stderr.is_empty());
+}
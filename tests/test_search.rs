@@ -149,3 +149,459 @@ fn search_invert_match_no_headers() {
     ];
     assert_eq!(got, expected);
 }
+
+fn people() -> Vec<Vec<String>> {
+    vec![
+        svec!["name", "email", "city"],
+        svec!["Alice", "alice@corp.com", "Boston"],
+        svec!["Adam", "adam@home.net", "Austin"],
+        svec!["Bob", "bob@corp.com", "Albany"],
+    ]
+}
+
+#[test]
+fn search_patterns_all() {
+    let wrk = Workdir::new("search_patterns_all");
+    wrk.create("data.csv", people());
+    let mut cmd = wrk.command("search");
+    cmd.arg("-e").arg("name=~^A").arg("-e").arg("email=~@corp\\.com$");
+    cmd.arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "email", "city"],
+        svec!["Alice", "alice@corp.com", "Boston"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn search_patterns_any() {
+    let wrk = Workdir::new("search_patterns_any");
+    wrk.create("data.csv", people());
+    let mut cmd = wrk.command("search");
+    cmd.arg("-e").arg("name=~^Ad").arg("-e").arg("city=~^Alb");
+    cmd.arg("--any").arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "email", "city"],
+        svec!["Adam", "adam@home.net", "Austin"],
+        svec!["Bob", "bob@corp.com", "Albany"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn search_patterns_negated_multiple_columns() {
+    let wrk = Workdir::new("search_patterns_negated_multiple_columns");
+    wrk.create("data.csv", people());
+    let mut cmd = wrk.command("search");
+    // Neither the name nor the city may start with "A".
+    cmd.arg("-e").arg("name,city!~^A").arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "email", "city"],
+    ];
+    assert_eq!(got, expected);
+
+    let mut cmd = wrk.command("search");
+    cmd.arg("-e").arg("1!~^a").arg("--ignore-case").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "email", "city"],
+        svec!["Bob", "bob@corp.com", "Albany"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn search_patterns_invalid() {
+    let wrk = Workdir::new("search_patterns_invalid");
+    wrk.create("data.csv", people());
+    let mut cmd = wrk.command("search");
+    cmd.arg("-e").arg("name^A").arg("data.csv");
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("search");
+    cmd.arg("-e").arg("nope=~^A").arg("data.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn search_patterns_quoted_column() {
+    let wrk = Workdir::new("search_patterns_quoted_column");
+    wrk.create("data.csv", vec![
+        svec!["a<b", "c=~d"],
+        svec!["1", "x"],
+        svec!["2", "y"],
+    ]);
+    let mut cmd = wrk.command("search");
+    cmd.arg("-e").arg("\"a<b\"=~1").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["a<b", "c=~d"], svec!["1", "x"]]);
+
+    let mut cmd = wrk.command("search");
+    cmd.arg("-e").arg("\"c=~d\"!~x").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["a<b", "c=~d"], svec!["2", "y"]]);
+}
+
+#[test]
+fn search_patterns_non_ascii_column() {
+    let wrk = Workdir::new("search_patterns_non_ascii_column");
+    wrk.create("data.csv", vec![
+        svec!["café", "prix"],
+        svec!["noir", "2"],
+        svec!["crème", "3"],
+    ]);
+    let mut cmd = wrk.command("search");
+    cmd.arg("-e").arg("café=~^cr").arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["café", "prix"],
+        svec!["crème", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
+fn amounts() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "amount", "when"],
+        svec!["1", "9", "2018-03-01"],
+        svec!["2", "10", "2018-03-01T23:30:00-02:00"],
+        svec!["3", "100", "2018-02-28T12:00:00Z"],
+        svec!["4", "", ""],
+        svec!["5", "n/a", "soon"],
+    ]
+}
+
+fn search_ids(wrk: &Workdir, args: &[&str]) -> Vec<String> {
+    let mut cmd = wrk.command("search");
+    cmd.args(args).arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    got.into_iter().skip(1).map(|row| row[0].clone()).collect()
+}
+
+#[test]
+fn search_greater_than_text() {
+    let wrk = Workdir::new("search_greater_than_text");
+    wrk.create("data.csv", amounts());
+
+    // Byte order, so "9" > "10".
+    let got = search_ids(&wrk, &["-s", "amount", "-g", "10"]);
+    assert_eq!(got, svec!["1", "2", "3", "5"]);
+}
+
+#[test]
+fn search_less_than_text_empty() {
+    let wrk = Workdir::new("search_less_than_text_empty");
+    wrk.create("data.csv", amounts());
+
+    // An empty field sorts before any bound as text.
+    let got = search_ids(&wrk, &["-s", "amount", "-l", "zzz"]);
+    assert_eq!(got, svec!["1", "2", "3", "4", "5"]);
+}
+
+#[test]
+fn search_greater_than_number() {
+    let wrk = Workdir::new("search_greater_than_number");
+    wrk.create("data.csv", amounts());
+
+    let got = search_ids(
+        &wrk, &["-s", "amount", "--type", "number", "-g", "10"]);
+    assert_eq!(got, svec!["2", "3"]);
+
+    let got = search_ids(
+        &wrk, &["-s", "amount", "--type", "number", "-g", "10",
+                "--exclusive"]);
+    assert_eq!(got, svec!["3"]);
+
+    let got = search_ids(
+        &wrk, &["-s", "amount", "--type", "number", "-l", "10"]);
+    assert_eq!(got, svec!["1", "2"]);
+}
+
+#[test]
+fn search_unparseable() {
+    let wrk = Workdir::new("search_unparseable");
+    wrk.create("data.csv", amounts());
+
+    let got = search_ids(
+        &wrk, &["-s", "amount", "--type", "number", "-l", "10",
+                "--unparseable", "match"]);
+    assert_eq!(got, svec!["1", "2", "5"]);
+
+    let mut cmd = wrk.command("search");
+    cmd.args(&["-s", "amount", "--type", "number", "-l", "10",
+               "--unparseable", "error", "data.csv"]);
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("search");
+    cmd.args(&["-s", "amount", "--type", "number", "-l", "ten", "data.csv"]);
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn search_date_range() {
+    let wrk = Workdir::new("search_date_range");
+    wrk.create("data.csv", amounts());
+
+    // The second row is 2018-03-02T01:30:00 in UTC.
+    let got = search_ids(
+        &wrk, &["--type", "date", "-e", "when>=2018-03-01",
+                "-e", "when<2018-03-02"]);
+    assert_eq!(got, svec!["1"]);
+
+    let got = search_ids(
+        &wrk, &["--type", "date", "-e", "when>2018-02-28 11:59:59.5"]);
+    assert_eq!(got, svec!["1", "2", "3"]);
+}
+
+#[test]
+fn search_range_pattern() {
+    let wrk = Workdir::new("search_range_pattern");
+    wrk.create("data.csv", amounts());
+
+    let got = search_ids(
+        &wrk, &["--type", "number", "-e", "amount>9", "-e", "amount<=100"]);
+    assert_eq!(got, svec!["2", "3"]);
+}
+
+#[test]
+fn search_patterns_file() {
+    let wrk = Workdir::new("search_patterns_file");
+    wrk.create("data.csv", people());
+    wrk.create_from_string("patterns.txt", "^Bo\r\n\ncorp\\.com$\n^Ad\n");
+    let mut cmd = wrk.command("search");
+    cmd.arg("-f").arg("patterns.txt").arg("-m").arg("matched");
+    cmd.arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "email", "city", "matched"],
+        svec!["Alice", "alice@corp.com", "Boston", "^Bo"],
+        svec!["Adam", "adam@home.net", "Austin", "^Ad"],
+        svec!["Bob", "bob@corp.com", "Albany", "^Bo"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn search_patterns_file_literal_select() {
+    let wrk = Workdir::new("search_patterns_file_literal_select");
+    wrk.create("data.csv", people());
+    wrk.create_from_string("patterns.txt", "home.net\n.com\n");
+    let mut cmd = wrk.command("search");
+    cmd.arg("-f").arg("patterns.txt").arg("--literal").arg("-s").arg("email");
+    cmd.arg("--match-column").arg("matched").arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "email", "city", "matched"],
+        svec!["Alice", "alice@corp.com", "Boston", ".com"],
+        svec!["Adam", "adam@home.net", "Austin", "home.net"],
+        svec!["Bob", "bob@corp.com", "Albany", ".com"],
+    ];
+    assert_eq!(got, expected);
+
+    // Without --literal, "." matches any character.
+    wrk.create_from_string("patterns.txt", "h.me\n");
+    let mut cmd = wrk.command("search");
+    cmd.arg("-f").arg("patterns.txt").arg("-s").arg("email").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "email", "city"],
+        svec!["Adam", "adam@home.net", "Austin"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn search_patterns_file_many_literals() {
+    let wrk = Workdir::new("search_patterns_file_many_literals");
+    wrk.create("data.csv", people());
+    let mut patterns: Vec<String> =
+        (0..50000).map(|i| format!("term{}", i)).collect();
+    patterns.push("HOME".to_owned());
+    patterns.push("Adam@".to_owned());
+    wrk.create_from_string("patterns.txt", &patterns.join("\n"));
+    let mut cmd = wrk.command("search");
+    cmd.arg("-f").arg("patterns.txt").arg("--literal").arg("-i");
+    cmd.arg("-m").arg("matched").arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "email", "city", "matched"],
+        svec!["Adam", "adam@home.net", "Austin", "HOME"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn search_patterns_file_invalid() {
+    let wrk = Workdir::new("search_patterns_file_invalid");
+    wrk.create("data.csv", people());
+    wrk.create_from_string("patterns.txt", "^A\n(\n");
+    let mut cmd = wrk.command("search");
+    cmd.arg("-f").arg("patterns.txt").arg("data.csv");
+
+    let o = cmd.output().unwrap();
+    assert!(!o.status.success());
+    let stderr = String::from_utf8_lossy(&o.stderr);
+    assert!(stderr.contains("'patterns.txt'"), "{}", stderr);
+}
+
+#[test]
+fn search_patterns_file_empty() {
+    let wrk = Workdir::new("search_patterns_file_empty");
+    wrk.create("data.csv", people());
+    wrk.create_from_string("patterns.txt", "\n\n");
+    let mut cmd = wrk.command("search");
+    cmd.arg("-f").arg("patterns.txt").arg("data.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn search_count() {
+    let wrk = Workdir::new("search_count");
+    wrk.create("data.csv", people());
+    let mut cmd = wrk.command("search");
+    cmd.arg("--count").arg("^A").arg("data.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "3");
+
+    let mut cmd = wrk.command("search");
+    cmd.arg("--count").arg("-v").arg("corp").arg("data.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "1");
+}
+
+#[test]
+fn search_flag() {
+    let wrk = Workdir::new("search_flag");
+    wrk.create("data.csv", people());
+    let mut cmd = wrk.command("search");
+    cmd.arg("--flag").arg("matched").arg("-s").arg("name").arg("^A");
+    cmd.arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "email", "city", "matched"],
+        svec!["Alice", "alice@corp.com", "Boston", "true"],
+        svec!["Adam", "adam@home.net", "Austin", "true"],
+        svec!["Bob", "bob@corp.com", "Albany", "false"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn search_row_numbers() {
+    let wrk = Workdir::new("search_row_numbers");
+    wrk.create("data.csv", people());
+    let mut cmd = wrk.command("search");
+    cmd.arg("--row-numbers").arg("corp").arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["row", "name", "email", "city"],
+        svec!["1", "Alice", "alice@corp.com", "Boston"],
+        svec!["3", "Bob", "bob@corp.com", "Albany"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn search_row_numbers_no_headers() {
+    let wrk = Workdir::new("search_row_numbers_no_headers");
+    wrk.create("data.csv", data(false));
+    let mut cmd = wrk.command("search");
+    cmd.arg("--row-numbers").arg("--no-headers").arg("^b").arg("data.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["1", "foobar", "barfoo"],
+        svec!["2", "a", "b"],
+        svec!["3", "barfoo", "foobar"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn search_count_with_flag() {
+    let wrk = Workdir::new("search_count_with_flag");
+    wrk.create("data.csv", people());
+    let mut cmd = wrk.command("search");
+    cmd.arg("--count").arg("--flag").arg("m").arg("^A").arg("data.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+fn numbered(n: usize) -> Vec<Vec<String>> {
+    let mut rows = vec![svec!["n", "parity"]];
+    for i in 0..n {
+        let parity = if i % 3 == 0 { "three" } else { "other" };
+        rows.push(vec![i.to_string(), parity.to_string()]);
+    }
+    rows
+}
+
+#[test]
+fn search_parallel_keeps_order() {
+    let wrk = Workdir::new("search_parallel_keeps_order");
+    wrk.create_indexed("data.csv", numbered(1000));
+
+    let mut cmd = wrk.command("search");
+    cmd.arg("--row-numbers").arg("three").arg("data.csv");
+    let expected: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(expected.len(), 335);
+
+    let mut cmd = wrk.command("search");
+    cmd.arg("--row-numbers").arg("three").arg("data.csv");
+    cmd.args(&["--jobs", "4"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn search_parallel_many_chunks() {
+    let wrk = Workdir::new("search_parallel_many_chunks");
+    // More chunks than can be searched at once with two jobs.
+    wrk.create_indexed("data.csv", numbered(55000));
+
+    let mut cmd = wrk.command("search");
+    cmd.arg("--row-numbers").arg("-v").arg("three").arg("data.csv");
+    let expected: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(expected.len(), 36667);
+
+    let mut cmd = wrk.command("search");
+    cmd.arg("--row-numbers").arg("-v").arg("three").arg("data.csv");
+    cmd.args(&["--jobs", "2"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn search_parallel_count() {
+    let wrk = Workdir::new("search_parallel_count");
+    wrk.create_indexed("data.csv", numbered(1000));
+
+    let mut cmd = wrk.command("search");
+    cmd.arg("--count").arg("-v").arg("three").arg("data.csv");
+    cmd.args(&["--jobs", "3"]);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "666");
+}
+
+#[test]
+fn search_parallel_error() {
+    let wrk = Workdir::new("search_parallel_error");
+    wrk.create_indexed("data.csv", numbered(1000));
+
+    let mut cmd = wrk.command("search");
+    cmd.args(&["-s", "parity", "--type", "number", "-g", "1"]);
+    cmd.args(&["--unparseable", "error", "--jobs", "4", "data.csv"]);
+    wrk.assert_err(&mut cmd);
+}
@@ -1,15 +1,25 @@
 use std::cmp;
+use std::fs;
 
 use workdir::Workdir;
 
 use {Csv, CsvData, qcheck};
 
 fn prop_sort(name: &str, rows: CsvData, headers: bool) -> bool {
+    prop_sort_args(name, rows, headers, &[])
+}
+
+fn prop_sort_args(
+    name: &str,
+    rows: CsvData,
+    headers: bool,
+    args: &[&str],
+) -> bool {
     let wrk = Workdir::new(name);
     wrk.create("in.csv", rows.clone());
 
     let mut cmd = wrk.command("sort");
-    cmd.arg("in.csv");
+    cmd.arg("in.csv").args(args);
     if !headers { cmd.arg("--no-headers"); }
 
     let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
@@ -40,6 +50,32 @@ fn prop_sort_no_headers() {
     qcheck(p as fn(CsvData) -> bool);
 }
 
+#[test]
+fn prop_sort_external_headers() {
+    fn p(rows: CsvData) -> bool {
+        prop_sort_args("prop_sort_external_headers", rows, true,
+                       &["--memory", "200"])
+    }
+    qcheck(p as fn(CsvData) -> bool);
+}
+
+#[test]
+fn prop_sort_external_no_headers() {
+    fn p(rows: CsvData) -> bool {
+        prop_sort_args("prop_sort_external_no_headers", rows, false,
+                       &["--memory", "200"])
+    }
+    qcheck(p as fn(CsvData) -> bool);
+}
+
+#[test]
+fn prop_sort_parallel() {
+    fn p(rows: CsvData) -> bool {
+        prop_sort_args("prop_sort_parallel", rows, true, &["--jobs", "4"])
+    }
+    qcheck(p as fn(CsvData) -> bool);
+}
+
 #[test]
 fn sort_select() {
     let wrk = Workdir::new("sort_select");
@@ -128,6 +164,396 @@ fn sort_reverse() {
     assert_eq!(got, expected);
 }
 
+#[test]
+fn sort_external_numeric_reverse() {
+    let wrk = Workdir::new("sort_external_numeric_reverse");
+    wrk.create("in.csv", vec![
+        svec!["N", "S"],
+        svec!["10", "a"],
+        svec!["LETTER", "b"],
+        svec!["2", "c"],
+        svec!["1", "d"],
+        svec!["2", "e"],
+    ]);
+    fs::create_dir_all(wrk.path("tmp")).unwrap();
+
+    let mut cmd = wrk.command("sort");
+    cmd.arg("-N").arg("-R")
+       .args(&["--memory", "1"]).args(&["--tmp-dir", "tmp"])
+       .arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["N", "S"],
+        svec!["10", "a"],
+        svec!["2", "c"],
+        svec!["2", "e"],
+        svec!["1", "d"],
+        svec!["LETTER", "b"],
+    ];
+    assert_eq!(got, expected);
+    // Temporary runs should be cleaned up.
+    assert_eq!(fs::read_dir(wrk.path("tmp")).unwrap().count(), 0);
+}
+
+#[test]
+fn sort_external_bad_memory() {
+    let wrk = Workdir::new("sort_external_bad_memory");
+    wrk.create("in.csv", vec![svec!["a"], svec!["b"]]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--memory", "lots"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--memory", "99999999999999G"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn sort_multi_key() {
+    let wrk = Workdir::new("sort_multi_key");
+    wrk.create("in.csv", vec![
+        svec!["country", "revenue", "name"],
+        svec!["us", "10", "bob"],
+        svec!["fr", "9", "Al"],
+        svec!["us", "100", "alice"],
+        svec!["fr", "20", "x"],
+        svec!["us", "100", "Aaron"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "country,revenue:num:desc,name:icase"])
+       .arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["country", "revenue", "name"],
+        svec!["fr", "20", "x"],
+        svec!["fr", "9", "Al"],
+        svec!["us", "100", "Aaron"],
+        svec!["us", "100", "alice"],
+        svec!["us", "10", "bob"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_key_overrides_flags() {
+    let wrk = Workdir::new("sort_key_overrides_flags");
+    wrk.create("in.csv", vec![
+        svec!["a", "b"],
+        svec!["1", "10"],
+        svec!["1", "9"],
+        svec!["2", "1"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.arg("-N").arg("-R").args(&["--select", "a:asc,b:lex"])
+       .arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["a", "b"],
+        svec!["1", "9"],
+        svec!["1", "10"],
+        svec!["2", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_nulls_last() {
+    let wrk = Workdir::new("sort_nulls_last");
+    wrk.create("in.csv", vec![
+        svec!["n"],
+        svec!["2"],
+        svec![""],
+        svec!["10"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "n:num:nulls-last"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["n"], svec!["2"], svec!["10"], svec![""]]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "n:num:desc:nulls-last"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["n"], svec!["10"], svec!["2"], svec![""]]);
+}
+
+#[test]
+fn sort_natural() {
+    let wrk = Workdir::new("sort_natural");
+    wrk.create("in.csv", vec![
+        svec!["f"],
+        svec!["file10"],
+        svec!["file2"],
+        svec!["file02"],
+        svec!["file1"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "f:natural"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["f"],
+        svec!["file1"],
+        svec!["file2"],
+        svec!["file02"],
+        svec!["file10"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_key_quoted_name() {
+    let wrk = Workdir::new("sort_key_quoted_name");
+    wrk.create("in.csv", vec![
+        svec!["a:desc", "b"],
+        svec!["1", "x"],
+        svec!["2", "y"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "\"a:desc\":desc"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["a:desc", "b"],
+        svec!["2", "y"],
+        svec!["1", "x"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_key_unknown_modifier() {
+    let wrk = Workdir::new("sort_key_unknown_modifier");
+    wrk.create("in.csv", vec![svec!["a"], svec!["1"]]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "a:sideways"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+fn stable_rows() -> Vec<Vec<String>> {
+    vec![
+        svec!["k", "v"],
+        svec!["b", "1"],
+        svec!["a", "2"],
+        svec!["b", "3"],
+        svec!["a", "4"],
+        svec!["c", "5"],
+        svec!["a", "6"],
+    ]
+}
+
+#[test]
+fn sort_stable() {
+    let wrk = Workdir::new("sort_stable");
+    wrk.create("in.csv", stable_rows());
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "k"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["a", "2"],
+        svec!["a", "4"],
+        svec!["a", "6"],
+        svec!["b", "1"],
+        svec!["b", "3"],
+        svec!["c", "5"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_stable_reverse_external() {
+    let wrk = Workdir::new("sort_stable_reverse_external");
+    wrk.create("in.csv", stable_rows());
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "k"]).arg("-R").args(&["--memory", "1"])
+       .arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["c", "5"],
+        svec!["b", "1"],
+        svec!["b", "3"],
+        svec!["a", "2"],
+        svec!["a", "4"],
+        svec!["a", "6"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_external_many_runs() {
+    let wrk = Workdir::new("sort_external_many_runs");
+    // With a tiny memory limit, every row is its own run, which is more
+    // runs than can be merged at once.
+    let mut rows: Vec<Vec<String>> =
+        (0..300).map(|i| vec![(i * 7 % 50).to_string(), i.to_string()])
+                .collect();
+    let mut input = vec![svec!["k", "v"]];
+    input.extend(rows.clone());
+    wrk.create("in.csv", input);
+    fs::create_dir_all(wrk.path("tmp")).unwrap();
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "k", "-N"]).args(&["--memory", "1"])
+       .args(&["--tmp-dir", "tmp"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    rows.sort_by_key(|row| row[0].parse::<u32>().unwrap());
+    let mut expected = vec![svec!["k", "v"]];
+    expected.extend(rows);
+    assert_eq!(got, expected);
+    assert_eq!(fs::read_dir(wrk.path("tmp")).unwrap().count(), 0);
+}
+
+#[test]
+fn sort_stable_parallel() {
+    let wrk = Workdir::new("sort_stable_parallel");
+    wrk.create("in.csv", stable_rows());
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "k"]).args(&["--jobs", "3"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["a", "2"],
+        svec!["a", "4"],
+        svec!["a", "6"],
+        svec!["b", "1"],
+        svec!["b", "3"],
+        svec!["c", "5"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_unique() {
+    let wrk = Workdir::new("sort_unique");
+    wrk.create("in.csv", stable_rows());
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "k"]).arg("--unique").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["a", "2"],
+        svec!["b", "1"],
+        svec!["c", "5"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_unique_icase() {
+    let wrk = Workdir::new("sort_unique_icase");
+    wrk.create("in.csv", vec![
+        svec!["n"],
+        svec!["alice"],
+        svec!["Bob"],
+        svec!["ALICE"],
+        svec!["Alice"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "n:icase"]).arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["n"], svec!["alice"], svec!["ALICE"], svec!["Alice"],
+        svec!["Bob"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "n:icase"]).arg("--unique").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["n"], svec!["alice"], svec!["Bob"]]);
+}
+
+#[test]
+fn sort_unique_natural() {
+    let wrk = Workdir::new("sort_unique_natural");
+    wrk.create("in.csv", vec![
+        svec!["f"],
+        svec!["a01"],
+        svec!["a2"],
+        svec!["a1"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "f:natural"]).arg("--unique").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["f"], svec!["a01"], svec!["a2"]]);
+}
+
+#[test]
+fn sort_unique_external() {
+    let wrk = Workdir::new("sort_unique_external");
+    wrk.create("in.csv", stable_rows());
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "k"]).arg("--unique")
+       .args(&["--memory", "150"]).arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["a", "2"],
+        svec!["b", "1"],
+        svec!["c", "5"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_unique_parallel() {
+    let wrk = Workdir::new("sort_unique_parallel");
+    wrk.create("in.csv", stable_rows());
+
+    let mut cmd = wrk.command("sort");
+    cmd.args(&["--select", "k"]).arg("--unique").args(&["--jobs", "4"])
+       .arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["k", "v"],
+        svec!["a", "2"],
+        svec!["b", "1"],
+        svec!["c", "5"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sort_unique_numeric() {
+    let wrk = Workdir::new("sort_unique_numeric");
+    wrk.create("in.csv", vec![
+        svec!["n"],
+        svec!["1.0"],
+        svec!["2"],
+        svec!["1"],
+    ]);
+
+    let mut cmd = wrk.command("sort");
+    cmd.arg("-N").arg("-u").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["n"], svec!["1.0"], svec!["2"]]);
+}
+
 /// Order `a` and `b` lexicographically using `Ord`
 pub fn iter_cmp<A, L, R>(mut a: L, mut b: R) -> cmp::Ordering
         where A: Ord, L: Iterator<Item=A>, R: Iterator<Item=A> {
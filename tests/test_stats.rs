@@ -1,5 +1,6 @@
 use std::borrow::ToOwned;
 use std::cmp;
+use std::io::Write;
 use std::process;
 
 use workdir::Workdir;
@@ -93,6 +94,12 @@ fn get_field_value(wrk: &Workdir, cmd: &mut process::Command, field: &str)
     if field == "median" { cmd.arg("--median"); }
     if field == "cardinality" { cmd.arg("--cardinality"); }
     if field == "mode" { cmd.arg("--mode"); }
+    if field == "q1" || field == "q3" || field == "iqr" {
+        cmd.arg("--quartiles");
+    }
+    if field.starts_with('p') {
+        cmd.arg("--percentiles").arg(&field[1..]);
+    }
 
     let mut rows: Vec<Vec<String>> = wrk.read_stdout(cmd);
     let headers = rows.remove(0);
@@ -123,6 +130,16 @@ stats_tests!(stats_infer_int_float, "type", &["1", "1.2"], "Float");
 stats_tests!(stats_infer_float_int, "type", &["1.2", "1"], "Float");
 stats_tests!(stats_infer_null_int_float_unicode, "type",
              &["", "1", "1.2", "a"], "Unicode");
+stats_tests!(stats_infer_bool, "type", &["true", "", "FALSE"], "Boolean");
+stats_tests!(stats_infer_bool_int, "type", &["true", "1"], "Unicode");
+stats_tests!(stats_infer_date, "type", &["2018-03-01", ""], "Date");
+stats_tests!(stats_infer_datetime, "type",
+             &["2018-03-01T12:30:00Z", "2018-03-01 08:00"], "DateTime");
+stats_tests!(stats_infer_date_datetime, "type",
+             &["2018-03-01", "2018-03-01T12:30:00+01:00"], "DateTime");
+stats_tests!(stats_infer_date_unicode, "type",
+             &["2018-03-01", "a"], "Unicode");
+stats_tests!(stats_infer_bad_date, "type", &["2018-02-30"], "Unicode");
 
 stats_tests!(stats_no_mean, "mean", &["a"], "");
 stats_tests!(stats_no_stddev, "stddev", &["a"], "");
@@ -156,12 +173,38 @@ stats_tests!(stats_min_mix, "min", &["2", "a", "1.1"], "1.1");
 stats_tests!(stats_max_mix, "max", &["2", "a", "1.1"], "a");
 stats_tests!(stats_min_null, "min", &["", "2", "1.1"], "1.1");
 stats_tests!(stats_max_null, "max", &["2", "1.1", ""], "2");
+stats_tests!(stats_min_date, "min",
+             &["2018-03-02", "2017-12-31T23:00:00-02:00", "2018-01-01"],
+             "2018-01-01");
+stats_tests!(stats_max_date, "max",
+             &["2018-03-02", "2018-03-01T23:00:00-02:00", ""],
+             "2018-03-01T23:00:00-02:00");
+stats_tests!(stats_min_bool, "min", &["true", "false"], "false");
+
+stats_tests!(stats_range_int, "range", &["5", "1", "3"], "4");
+stats_tests!(stats_range_float, "range", &["1.5", "4"], "2.5");
+stats_tests!(stats_range_unicode, "range", &["a", "b"], "");
+stats_tests!(stats_range_date, "range", &["2018-03-04", "2018-03-01"], "P3D");
+stats_tests!(stats_range_datetime, "range",
+             &["2018-03-01T00:00:00Z", "2018-03-01T01:02:03.5Z"],
+             "PT1H2M3.5S");
+stats_tests!(stats_range_same, "range", &["2018-03-01", "2018-03-01"],
+             "PT0S");
 
 stats_tests!(stats_len_min, "min_length", &["aa", "a"], "1");
 stats_tests!(stats_len_max, "max_length", &["a", "aa"], "2");
 stats_tests!(stats_len_min_null, "min_length", &["", "aa", "a"], "0");
 stats_tests!(stats_len_max_null, "max_length", &["a", "aa", ""], "2");
 
+stats_tests!(stats_len_mean, "mean_length", &["a", "aa", "abc"], "2");
+stats_tests!(stats_len_mean_null, "mean_length", &["", "aa", "aaaa"], "2");
+
+stats_tests!(stats_null_count, "null_count", &["", "a", "", "1"], "2");
+stats_tests!(stats_null_count_none, "null_count", &["a", "1"], "0");
+stats_tests!(stats_null_percent, "null_percent", &["", "a", "b", "c"], "25");
+stats_tests!(stats_non_empty_count, "non_empty_count",
+             &["", "a", "", "1", "2.5"], "3");
+
 stats_tests!(stats_mean, "mean", &["5", "15", "10"], "10");
 stats_tests!(stats_stddev, "stddev", &["1", "2", "3"], "0.816496580927726");
 stats_tests!(stats_mean_null, "mean", &["", "5", "15", "10"], "10");
@@ -180,6 +223,14 @@ stats_tests!(stats_median_even, "median", &["1", "2", "3", "4"], "2.5");
 stats_tests!(stats_median_even_null, "median",
              &["", "1", "2", "3", "4"], "2.5");
 stats_tests!(stats_median_mix, "median", &["1", "2.5", "3"], "2.5");
+stats_tests!(stats_q1, "q1", &["1", "2", "3", "4", "5"], "2");
+stats_tests!(stats_q3, "q3", &["5", "4", "", "3", "2", "1"], "4");
+stats_tests!(stats_iqr, "iqr", &["1", "2", "3", "4"], "1.5");
+stats_tests!(stats_p90, "p90", &["10", "20", "30", "40", "50"], "46");
+stats_tests!(stats_p0, "p0", &["3", "1", "2"], "1");
+stats_tests!(stats_p100, "p100", &["3", "1", "2"], "3");
+stats_tests!(stats_p50_mix, "p50", &["1", "2.5", "3"], "2.5");
+stats_tests!(stats_no_p90, "p90", &["a"], "");
 
 mod stats_infer_nothing {
     // Only test CSV data with headers.
@@ -203,6 +254,21 @@ mod stats_zero_mean {
     stats_test_headers!(stats_zero_mean, "mean", &[], "");
 }
 
+mod stats_zero_null_count {
+    use super::test_stats;
+    stats_test_headers!(stats_zero_null_count, "null_count", &[], "0");
+}
+
+mod stats_zero_null_percent {
+    use super::test_stats;
+    stats_test_headers!(stats_zero_null_percent, "null_percent", &[], "");
+}
+
+mod stats_zero_mean_length {
+    use super::test_stats;
+    stats_test_headers!(stats_zero_mean_length, "mean_length", &[], "");
+}
+
 mod stats_zero_median {
     use super::test_stats;
     stats_test_headers!(stats_zero_median, "median", &[], "");
@@ -213,3 +279,258 @@ mod stats_header_fields {
     stats_test_headers!(stats_header_field_name, "field", &["a"], "header");
     stats_test_no_headers!(stats_header_no_field_name, "field", &["a"], "0");
 }
+
+fn grouped_data() -> Vec<Vec<String>> {
+    vec![
+        svec!["city", "n"],
+        svec!["Boston", "1"],
+        svec!["Austin", "a"],
+        svec!["Boston", "3"],
+        svec!["Austin", "b"],
+        svec!["Boston", "5"],
+    ]
+}
+
+fn grouped_stats(wrk: &Workdir) -> Vec<Vec<String>> {
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--group-by", "city", "--select", "n", "--median"]);
+    cmd.arg("in.csv");
+    let rows: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let cols: Vec<usize> = ["city", "field", "type", "sum", "median"]
+        .iter()
+        .map(|&name| rows[0].iter().position(|h| h == name).unwrap())
+        .collect();
+    rows.into_iter()
+        .map(|row| cols.iter().map(|&i| row[i].clone()).collect())
+        .collect()
+}
+
+#[test]
+fn stats_group_by() {
+    let wrk = Workdir::new("stats_group_by");
+    wrk.create("in.csv", grouped_data());
+
+    let expected = vec![
+        svec!["city", "field", "type", "sum", "median"],
+        svec!["Boston", "n", "Integer", "9", "3"],
+        svec!["Austin", "n", "Unicode", "", ""],
+    ];
+    assert_eq!(grouped_stats(&wrk), expected);
+}
+
+#[test]
+fn stats_group_by_index() {
+    let wrk = Workdir::new("stats_group_by_index");
+    wrk.create_indexed("in.csv", grouped_data());
+
+    let expected = vec![
+        svec!["city", "field", "type", "sum", "median"],
+        svec!["Boston", "n", "Integer", "9", "3"],
+        svec!["Austin", "n", "Unicode", "", ""],
+    ];
+    assert_eq!(grouped_stats(&wrk), expected);
+}
+
+#[test]
+fn stats_group_by_stdin() {
+    let wrk = Workdir::new("stats_group_by_stdin");
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--group-by", "city", "--select", "n"]);
+    cmd.stdin(process::Stdio::piped()).stdout(process::Stdio::piped());
+
+    let mut child = cmd.spawn().unwrap();
+    child.stdin.take().unwrap()
+         .write_all(b"city,n\nBoston,1\nAustin,2\nBoston,3\n").unwrap();
+    let o = child.wait_with_output().unwrap();
+    assert!(o.status.success());
+    let got = String::from_utf8(o.stdout).unwrap();
+    let got: Vec<Vec<&str>> = got.lines()
+        .map(|line| line.split(',').take(4).collect())
+        .collect();
+    let expected = vec![
+        vec!["city", "field", "type", "sum"],
+        vec!["Boston", "n", "Integer", "4"],
+        vec!["Austin", "n", "Integer", "2"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn stats_group_by_no_headers() {
+    let wrk = Workdir::new("stats_group_by_no_headers");
+    wrk.create("in.csv", vec![svec!["x", "1"], svec!["y", "2"]]);
+
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--group-by", "1", "--select", "2", "--no-headers"]);
+    cmd.arg("in.csv");
+    let rows: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let got: Vec<Vec<String>> = rows.into_iter()
+        .map(|row| row[0..4].to_vec())
+        .collect();
+    let expected = vec![
+        svec!["1", "field", "type", "sum"],
+        svec!["x", "0", "Integer", "1"],
+        svec!["y", "0", "Integer", "2"],
+    ];
+    assert_eq!(got, expected);
+}
+
+fn percentiles(wrk: &Workdir, args: &[&str]) -> Vec<f64> {
+    let mut cmd = wrk.command("stats");
+    cmd.args(args).arg("in.csv");
+    let rows: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let start = rows[0].iter().position(|h| h == "p1").unwrap();
+    rows[1][start..].iter().map(|v| v.parse().unwrap()).collect()
+}
+
+#[test]
+fn stats_percentiles_approx() {
+    let wrk = Workdir::new("stats_percentiles_approx");
+    let mut rows = vec![svec!["n"]];
+    for i in 0..10000 {
+        rows.push(vec![((i * 7919) % 10000).to_string()]);
+    }
+    wrk.create_indexed("in.csv", rows);
+
+    let args = &["--percentiles", "1,50,99,99.9"];
+    let exact = percentiles(&wrk, args);
+    let expected = [99.99, 4999.5, 9899.01, 9989.001];
+    for (e, x) in exact.iter().zip(expected.iter()) {
+        assert!((e - x).abs() < 1e-6, "{} != {}", e, x);
+    }
+
+    let approx = percentiles(&wrk, &[args[0], args[1], "--approx"]);
+    for (e, a) in exact.iter().zip(approx.iter()) {
+        assert!((e - a).abs() < 10.0, "{} is too far from {}", a, e);
+    }
+}
+
+#[test]
+fn stats_percentiles_invalid() {
+    let wrk = Workdir::new("stats_percentiles_invalid");
+    wrk.create("in.csv", vec![svec!["n"], svec!["1"]]);
+
+    for list in &["101", "a", "50,"] {
+        let mut cmd = wrk.command("stats");
+        cmd.arg("--percentiles").arg(list).arg("in.csv");
+        wrk.assert_err(&mut cmd);
+    }
+}
+
+#[test]
+fn stats_cardinality_approx() {
+    let wrk = Workdir::new("stats_cardinality_approx");
+    let mut rows = vec![svec!["id", "small"]];
+    for i in 0..20000 {
+        rows.push(vec![(i / 2).to_string(), (i % 7).to_string()]);
+    }
+    wrk.create_indexed("in.csv", rows);
+
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--cardinality", "--approx", "--error-rate", "0.02"]);
+    cmd.arg("in.csv");
+    let rows: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let col = rows[0].iter().position(|h| h == "cardinality").unwrap();
+    let ids: f64 = rows[1][col].parse().unwrap();
+    assert!((ids - 10000.0).abs() < 600.0, "{} is too far from 10000", ids);
+    assert_eq!(rows[2][col], "7");
+
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--cardinality", "--error-rate", "1.5"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--cardinality", "--error-rate", "0.001"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn stats_headers_order() {
+    let wrk = Workdir::new("stats_headers_order");
+    wrk.create("in.csv", vec![svec!["n"], svec!["1"]]);
+
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--everything", "--percentiles", "90"]).arg("in.csv");
+    let rows: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(rows[0], svec![
+        "field", "type", "sum", "min", "max", "min_length", "max_length",
+        "mean", "stddev", "median", "mode", "cardinality", "range",
+        "mean_length", "null_count", "null_percent", "non_empty_count", "q1",
+        "q3", "iqr", "p90",
+    ]);
+}
+
+#[test]
+fn stats_date_formats() {
+    let wrk = Workdir::new("stats_date_formats");
+    wrk.create("in.csv", vec![
+        svec!["day", "time"],
+        svec!["31/12/2017", "Mar 1, 2018 9:05"],
+        svec!["2018-01-02", "mar 3, 2018 10:00"],
+        svec!["1/1/2018", ""],
+    ]);
+
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--date-formats", "%d/%m/%Y;%b %d, %Y %H:%M"]).arg("in.csv");
+    let rows: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let range = rows[0].iter().position(|h| h == "range").unwrap();
+    let got: Vec<Vec<String>> = rows.into_iter()
+        .map(|row| {
+            let mut got = row[0..5].to_vec();
+            got.push(row[range].clone());
+            got
+        })
+        .collect();
+    let expected = vec![
+        svec!["field", "type", "sum", "min", "max", "range"],
+        svec!["day", "Date", "", "31/12/2017", "2018-01-02", "P2D"],
+        svec!["time", "DateTime", "", "Mar 1, 2018 9:05", "mar 3, 2018 10:00",
+              "P2DT55M"],
+    ];
+    assert_eq!(got, expected);
+
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--date-formats", "%d/%m"]).arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn stats_json() {
+    let wrk = Workdir::new("stats_json");
+    wrk.create("in.csv", grouped_data());
+
+    let mut cmd = wrk.command("stats");
+    cmd.args(&["--group-by", "city", "--select", "n", "--mode", "--json"]);
+    cmd.arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+[
+  {\"group\":{\"city\":\"Boston\"},\"field\":\"n\",\"type\":\"Integer\",\
+\"sum\":9,\"min\":1,\"max\":5,\"min_length\":1,\"max_length\":1,\
+\"mean\":3,\"stddev\":1.632993161855452,\"mode\":\"N/A\",\"range\":4,\
+\"mean_length\":1,\"null_count\":0,\"null_percent\":0,\"non_empty_count\":3},
+  {\"group\":{\"city\":\"Austin\"},\"field\":\"n\",\"type\":\"Unicode\",\
+\"sum\":null,\"min\":\"a\",\"max\":\"b\",\"min_length\":1,\"max_length\":1,\
+\"mean\":null,\"stddev\":null,\"mode\":\"N/A\",\"range\":null,\
+\"mean_length\":1,\"null_count\":0,\"null_percent\":0,\"non_empty_count\":2}
+]";
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn stats_json_empty() {
+    let wrk = Workdir::new("stats_json_empty");
+    wrk.create("in.csv", vec![svec!["n"]]);
+
+    let mut cmd = wrk.command("stats");
+    cmd.arg("--json").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+[
+  {\"field\":\"n\",\"type\":\"NULL\",\"sum\":null,\"min\":null,\
+\"max\":null,\"min_length\":null,\"max_length\":null,\"mean\":null,\
+\"stddev\":null,\"range\":null,\"mean_length\":null,\"null_count\":0,\
+\"null_percent\":null,\"non_empty_count\":0}
+]";
+    assert_eq!(got, expected);
+}
@@ -0,0 +1,88 @@
+use workdir::Workdir;
+
+fn transpose(name: &str, rows: Vec<Vec<String>>, multipass: bool)
+            -> Vec<Vec<String>> {
+    let wrk = Workdir::new(name).flexible(true);
+    wrk.create("in.csv", rows);
+    let mut cmd = wrk.command("transpose");
+    cmd.arg("in.csv");
+    if multipass {
+        cmd.arg("--multipass");
+    }
+    wrk.read_stdout(&mut cmd)
+}
+
+#[test]
+fn transpose_square() {
+    let rows = vec![
+        svec!["h1", "h2", "h3"],
+        svec!["a", "b", "c"],
+        svec!["d", "e", "f"],
+    ];
+    let expected = vec![
+        svec!["h1", "a", "d"],
+        svec!["h2", "b", "e"],
+        svec!["h3", "c", "f"],
+    ];
+    assert_eq!(transpose("transpose_square", rows.clone(), false), expected);
+    assert_eq!(transpose("transpose_square_multipass", rows, true), expected);
+}
+
+#[test]
+fn transpose_wide() {
+    let rows = vec![
+        svec!["name", "2016", "2017", "2018", "2019"],
+        svec!["sales", "1", "2", "3", "4"],
+    ];
+    let expected = vec![
+        svec!["name", "sales"],
+        svec!["2016", "1"],
+        svec!["2017", "2"],
+        svec!["2018", "3"],
+        svec!["2019", "4"],
+    ];
+    assert_eq!(transpose("transpose_wide", rows, false), expected);
+}
+
+#[test]
+fn transpose_ragged() {
+    let rows = vec![
+        svec!["a", "b"],
+        svec!["c"],
+        svec!["d", "e", "f"],
+    ];
+    let expected = vec![
+        svec!["a", "c", "d"],
+        svec!["b", "", "e"],
+        svec!["", "", "f"],
+    ];
+    assert_eq!(transpose("transpose_ragged", rows.clone(), false), expected);
+    assert_eq!(transpose("transpose_ragged_multipass", rows, true), expected);
+}
+
+#[test]
+fn transpose_twice_roundtrips() {
+    let wrk = Workdir::new("transpose_twice_roundtrips");
+    let rows = vec![
+        svec!["h1", "h2"],
+        svec!["a", "b"],
+        svec!["c", "d"],
+    ];
+    wrk.create("in.csv", rows.clone());
+    let mut cmd = wrk.command("transpose");
+    cmd.arg("in.csv").args(&["-o", "t.csv"]);
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("transpose");
+    cmd.arg("t.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, rows);
+}
+
+#[test]
+fn transpose_multipass_stdin() {
+    let wrk = Workdir::new("transpose_multipass_stdin");
+    let mut cmd = wrk.command("transpose");
+    cmd.arg("--multipass");
+    wrk.assert_err(&mut cmd);
+}
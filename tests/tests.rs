@@ -35,14 +35,23 @@ mod workdir;
 
 mod test_cat;
 mod test_count;
+mod test_dedup;
+mod test_filter;
 mod test_fixlengths;
 mod test_flatten;
 mod test_fmt;
 mod test_frequency;
+mod test_groupby;
 mod test_headers;
+mod test_histogram;
 mod test_index;
 mod test_join;
+mod test_map;
+mod test_melt;
 mod test_partition;
+mod test_pivot;
+mod test_rename;
+mod test_replace;
 mod test_reverse;
 mod test_search;
 mod test_select;
@@ -51,6 +60,7 @@ mod test_sort;
 mod test_split;
 mod test_stats;
 mod test_table;
+mod test_transpose;
 
 fn qcheck<T: Testable>(p: T) {
     QuickCheck::new().gen(StdGen::new(thread_rng(), 5)).quickcheck(p);
@@ -1,7 +1,7 @@
 use std::env;
 use std::fmt;
 use std::fs;
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process;
 use std::str::FromStr;
@@ -58,6 +58,12 @@ impl Workdir {
         wtr.flush().unwrap();
     }
 
+    pub fn create_from_string(&self, name: &str, data: &str) {
+        let mut file = fs::File::create(&self.path(name)).unwrap();
+        file.write_all(data.as_bytes()).unwrap();
+        file.flush().unwrap();
+    }
+
     pub fn create_indexed<T: Csv>(&self, name: &str, rows: T) {
         self.create(name, rows);
 